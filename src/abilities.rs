@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::player::{player_shape, BPPlayerMarker};
+use crate::replay::BPReplayPlayback;
+use crate::save::BPSaveData;
+use crate::state::BPGameState;
+
+/// Radius of an ability pickup, in world units.
+pub const PICKUP_RADIUS: f32 = 15.;
+
+// Moves beyond running and jumping are locked until a pickup of the level unlocks them.
+// Unlocks go to `BPSaveData`, are shared by every player and kept from one run to the next.
+// A replay starts with the abilities it was recorded with and leaves the save alone.
+pub struct BPAbilitiesPlugin;
+
+impl Plugin for BPAbilitiesPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<BPAbilities>()
+            .init_resource::<BPSaveData>()
+            .add_systems(FixedUpdate, collect_pickups
+                .after(PhysicsSet::Writeback)
+                .run_if(in_state(BPGameState::InGame)))
+            .add_systems(Update, apply_abilities.run_if(resource_changed::<BPSaveData>.and(not(resource_exists::<BPReplayPlayback>))));
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum BPAbility {
+    DoubleJump,
+    Dash,
+    /// Sliding down walls and jumping off them.
+    WallJump,
+    GroundPound,
+}
+
+impl BPAbility {
+    pub const ALL: [BPAbility; 4] = [BPAbility::DoubleJump, BPAbility::Dash, BPAbility::WallJump, BPAbility::GroundPound];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BPAbility::DoubleJump => "Double jump",
+            BPAbility::Dash => "Dash",
+            BPAbility::WallJump => "Wall jump",
+            BPAbility::GroundPound => "Ground pound",
+        }
+    }
+
+    /// The ability after this one in `ALL`, looping back to the first one.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|ability| *ability == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// What a player is allowed to do, copied from `BPSaveData`.
+#[derive(Component, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+#[serde(default)]
+pub struct BPAbilities {
+    pub double_jump: bool,
+    pub dash: bool,
+    pub wall_jump: bool,
+    pub ground_pound: bool,
+}
+
+impl BPAbilities {
+    pub fn has(&self, ability: BPAbility) -> bool {
+        match ability {
+            BPAbility::DoubleJump => self.double_jump,
+            BPAbility::Dash => self.dash,
+            BPAbility::WallJump => self.wall_jump,
+            BPAbility::GroundPound => self.ground_pound,
+        }
+    }
+
+    pub fn unlock(&mut self, ability: BPAbility) {
+        match ability {
+            BPAbility::DoubleJump => self.double_jump = true,
+            BPAbility::Dash => self.dash = true,
+            BPAbility::WallJump => self.wall_jump = true,
+            BPAbility::GroundPound => self.ground_pound = true,
+        }
+    }
+}
+
+/// Unlocks its ability for every player when one of them touches it.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BPAbilityPickup(pub BPAbility);
+
+fn collect_pickups(mut commands: Commands,
+                   mut player_query: Query<(&Transform, &mut BPAbilities), With<BPPlayerMarker>>,
+                   pickup_query: Query<(Entity, &Transform, &BPAbilityPickup)>,
+                   playback: Option<Res<BPReplayPlayback>>,
+                   mut save_data: ResMut<BPSaveData>) {
+    let reach = player_shape().radius + PICKUP_RADIUS;
+
+    for (entity, pickup_transform, pickup) in &pickup_query {
+        let position = pickup_transform.translation.xy();
+        // Pickups of abilities unlocked in an earlier run go away as well
+        let unlocked = player_query.iter().any(|(_, abilities)| abilities.has(pickup.0));
+        let touched = player_query.iter().any(|(transform, _)| transform.translation.xy().distance(position) < reach);
+        if !unlocked && !touched {
+            continue;
+        }
+
+        commands.entity(entity).despawn();
+        if unlocked {
+            continue;
+        }
+
+        // Players get it on this tick, so a replay unlocks it on the same one
+        for (_, mut abilities) in &mut player_query {
+            abilities.unlock(pickup.0);
+        }
+        if playback.is_none() {
+            save_data.abilities.unlock(pickup.0);
+        }
+        info!("{} unlocked", pickup.0.label());
+    }
+}
+
+fn apply_abilities(save_data: Res<BPSaveData>,
+                   mut query: Query<&mut BPAbilities, With<BPPlayerMarker>>) {
+    for mut abilities in &mut query {
+        if *abilities != save_data.abilities {
+            *abilities = save_data.abilities;
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use bevy::audio::Volume;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use rand::Rng;
+use crate::player::BPGameplayMessage;
+use crate::rng::{BPRng, BPRngStream};
+use crate::settings::BPSettings;
+
+pub struct BPAudioPlugin;
+
+impl Plugin for BPAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, setup_sfx_assets)
+            .add_systems(Update, (
+                play_gameplay_sfx,
+                update_sfx_volume.run_if(resource_changed::<BPSettings>),
+            ));
+    }
+}
+
+/// Volume categories. Each one is scaled by its own setting and by the master volume.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BPAudioBus {
+    Music,
+    Sfx,
+}
+
+impl BPAudioBus {
+    pub fn volume(self, settings: &BPSettings) -> f32 {
+        let bus = match self {
+            BPAudioBus::Music => settings.music_volume,
+            BPAudioBus::Sfx => settings.sfx_volume,
+        };
+        settings.master_volume * bus
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum BPSfx {
+    Jump,
+    Land,
+    Stomp,
+    Death,
+    Victory,
+}
+
+impl BPSfx {
+    pub const ALL: [BPSfx; 5] = [BPSfx::Jump, BPSfx::Land, BPSfx::Stomp, BPSfx::Death, BPSfx::Victory];
+
+    fn path(self) -> &'static str {
+        match self {
+            BPSfx::Jump => "audio/sfx/jump.ogg",
+            BPSfx::Land => "audio/sfx/land.ogg",
+            BPSfx::Stomp => "audio/sfx/stomp.ogg",
+            BPSfx::Death => "audio/sfx/death.ogg",
+            BPSfx::Victory => "audio/sfx/victory.ogg",
+        }
+    }
+
+    /// Volume of the sound itself, before the bus volume is applied.
+    fn base_volume(self) -> f32 {
+        match self {
+            BPSfx::Land => 0.5,
+            _ => 1.0,
+        }
+    }
+
+    /// How much the playback speed (and so the pitch) may drift from 1, to avoid the machine-gun effect.
+    fn pitch_variation(self) -> f32 {
+        match self {
+            BPSfx::Jump | BPSfx::Land | BPSfx::Stomp => 0.1,
+            BPSfx::Death | BPSfx::Victory => 0.,
+        }
+    }
+
+    /// How many instances of this sound can play at the same time.
+    fn max_instances(self) -> usize {
+        match self {
+            BPSfx::Jump | BPSfx::Land => 3,
+            BPSfx::Stomp => 2,
+            BPSfx::Death | BPSfx::Victory => 1,
+        }
+    }
+
+    fn from_message(message: &BPGameplayMessage) -> Self {
+        match message {
+            BPGameplayMessage::Jumped => BPSfx::Jump,
+            BPGameplayMessage::Landed => BPSfx::Land,
+            BPGameplayMessage::Stomped => BPSfx::Stomp,
+            BPGameplayMessage::Died => BPSfx::Death,
+            BPGameplayMessage::Victory => BPSfx::Victory,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct BPSfxAssets(pub HashMap<BPSfx, Handle<AudioSource>>);
+
+/// A sound effect currently playing. The entity despawns itself once the sound is over.
+#[derive(Component)]
+pub struct BPSfxInstance(pub BPSfx);
+
+fn setup_sfx_assets(mut commands: Commands,
+                    asset_server: Res<AssetServer>) {
+    let handles = BPSfx::ALL
+        .into_iter()
+        .map(|sfx| (sfx, asset_server.load(sfx.path())))
+        .collect();
+
+    commands.insert_resource(BPSfxAssets(handles));
+}
+
+fn play_gameplay_sfx(mut commands: Commands,
+                     mut gameplay_messages: MessageReader<BPGameplayMessage>,
+                     sfx_assets: Res<BPSfxAssets>,
+                     settings: Res<BPSettings>,
+                     instance_query: Query<&BPSfxInstance>,
+                     mut rng: ResMut<BPRng>) {
+    let rng = rng.stream(BPRngStream::Audio);
+
+    // Instances spawned this frame are not in the query yet, so count them separately
+    let mut playing: HashMap<BPSfx, usize> = HashMap::default();
+    for instance in &instance_query {
+        *playing.entry(instance.0).or_default() += 1;
+    }
+
+    for message in gameplay_messages.read() {
+        let sfx = BPSfx::from_message(message);
+
+        let count = playing.entry(sfx).or_default();
+        if *count >= sfx.max_instances() {
+            continue;
+        }
+        *count += 1;
+
+        let Some(handle) = sfx_assets.0.get(&sfx) else {
+            continue;
+        };
+
+        let variation = sfx.pitch_variation();
+        let speed = if variation > 0. { rng.random_range(1. - variation..1. + variation) } else { 1. };
+        let volume = sfx.base_volume() * BPAudioBus::Sfx.volume(&settings);
+
+        commands.spawn((
+            BPSfxInstance(sfx),
+            AudioPlayer::new(handle.clone()),
+            PlaybackSettings::DESPAWN
+                .with_volume(Volume::Linear(volume))
+                .with_speed(speed),
+        ));
+    }
+}
+
+fn update_sfx_volume(settings: Res<BPSettings>,
+                     mut sink_query: Query<(&mut AudioSink, &BPSfxInstance)>) {
+    for (mut sink, instance) in &mut sink_query {
+        sink.set_volume(Volume::Linear(instance.0.base_volume() * BPAudioBus::Sfx.volume(&settings)));
+    }
+}
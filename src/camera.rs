@@ -1,12 +1,15 @@
 use bevy::post_process::bloom::Bloom;
 use bevy::prelude::*;
 use crate::player::BPPlayerMarker;
+use crate::settings::BPSettings;
 
 pub struct BPCameraPlugin;
 
 impl Plugin for BPCameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, camera_follow);
+        app
+            .init_resource::<BPCameraShake>()
+            .add_systems(Update, (remove_camera_shake, camera_follow, camera_shake).chain());
     }
 }
 
@@ -16,6 +19,19 @@ pub struct BPCameraMarker;
 #[derive(Component)]
 pub struct BPCameraSpeed(pub f32);
 
+/// Trauma based screen shake. Add trauma on impacts, it decays on its own.
+#[derive(Resource, Default)]
+pub struct BPCameraShake {
+    pub trauma: f32,
+    offset: Vec2,
+}
+
+impl BPCameraShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+}
+
 #[derive(Bundle)]
 pub struct BPCameraBundle {
     pub marker: BPCameraMarker,
@@ -31,25 +47,78 @@ impl BPCameraBundle {
         Self {
             marker: BPCameraMarker {},
             speed: BPCameraSpeed(speed),
-            camera: Camera2d::default(),
+            camera: Camera2d,
             bloom: Bloom::NATURAL,
         }
     }
 }
 
+/// Space kept around the players when framing them, in world units.
+const FRAMING_MARGIN: f32 = 400.0;
+/// How far the camera may zoom out to keep every player on screen.
+const MAX_ZOOM_OUT: f32 = 2.5;
+
+// Frames every player: the camera aims at the middle of their bounding box
+// and zooms out when they drift apart.
+#[allow(clippy::type_complexity)]
 fn camera_follow(player_pos: Query<&Transform, With<BPPlayerMarker>>,
-                 mut camera_query: Query<(&mut Transform, &BPCameraSpeed), (With<BPCameraMarker>, Without<BPPlayerMarker>)>,
+                 mut camera_query: Query<(&mut Transform, &mut Projection, &Camera, &BPCameraSpeed), (With<BPCameraMarker>, Without<BPPlayerMarker>)>,
                  time: Res<Time>) {
-    if let Ok(player_pos) = player_pos.single() {
-        if let Ok(mut camera_query) = camera_query.single_mut() {
-            let target = Vec3::new(
-                player_pos.translation.x,
-                player_pos.translation.y,
-                camera_query.0.translation.z
-            );
-
-            let t = camera_query.1.0 * time.delta().as_secs_f32();
-            camera_query.0.translation = camera_query.0.translation.lerp(target, t);
-        }
+    let Ok((mut camera_transform, mut projection, camera, speed)) = camera_query.single_mut() else {
+        return;
+    };
+
+    let mut min = Vec2::splat(f32::MAX);
+    let mut max = Vec2::splat(f32::MIN);
+    for player_pos in &player_pos {
+        min = min.min(player_pos.translation.xy());
+        max = max.max(player_pos.translation.xy());
+    }
+
+    // No player alive, stay where we are
+    if min.x > max.x {
+        return;
+    }
+
+    let center = (min + max) / 2.;
+    let target = Vec3::new(center.x, center.y, camera_transform.translation.z);
+
+    let t = speed.0 * time.delta().as_secs_f32();
+    camera_transform.translation = camera_transform.translation.lerp(target, t);
+
+    if let Projection::Orthographic(orthographic) = projection.as_mut()
+        && let Some(viewport) = camera.logical_viewport_size() {
+        let extent = max - min + Vec2::splat(FRAMING_MARGIN);
+        let target_scale = (extent / viewport).max_element().clamp(1.0, MAX_ZOOM_OUT);
+        orthographic.scale = orthographic.scale.lerp(target_scale, t);
     }
+}
+
+// Takes last frame's offset back out, so the follow lerp works on the real position.
+fn remove_camera_shake(mut shake: ResMut<BPCameraShake>,
+                       mut camera_query: Query<&mut Transform, With<BPCameraMarker>>) {
+    let Ok(mut transform) = camera_query.single_mut() else {
+        return;
+    };
+
+    transform.translation -= shake.offset.extend(0.);
+    shake.offset = Vec2::ZERO;
+}
+
+fn camera_shake(mut shake: ResMut<BPCameraShake>,
+                settings: Res<BPSettings>,
+                mut camera_query: Query<&mut Transform, With<BPCameraMarker>>,
+                time: Res<Time>) {
+    let Ok(mut transform) = camera_query.single_mut() else {
+        return;
+    };
+
+    shake.trauma = (shake.trauma - 1.5 * time.delta_secs()).max(0.);
+
+    // Squaring the trauma makes small hits subtle and big hits violent
+    let strength = shake.trauma * shake.trauma * settings.screen_shake * 20.0;
+    let t = time.elapsed_secs();
+    shake.offset = Vec2::new((t * 50.0).sin(), (t * 43.0).cos()) * strength;
+
+    transform.translation += shake.offset.extend(0.);
 }
\ No newline at end of file
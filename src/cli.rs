@@ -0,0 +1,173 @@
+use std::path::PathBuf;
+use bevy::prelude::*;
+use bevy_platformer::state::BPGameState;
+
+pub const USAGE: &str = "\
+Usage: bevy_platformer [OPTIONS]
+
+Options:
+  --level <index>          Level to start on, starting at 0
+  --seed <number>          Seed of the random number generator
+  --window-size <WxH>      Size of the window, e.g. 1280x720
+  --state <state>          State to start in: in-game, game-over, victory or editor, editor implies --debug
+  --debug                  Enable the debug tools (inspector, physics lines) in a release build
+  --physics-debug          Draw the physics colliders, implies --debug
+  --replay <file>          Play a recorded run back instead of reading the devices
+  --headless <ticks>       Simulate without a window for at most this many ticks, then exit
+  --help                   Print this message
+
+Exit codes of --headless: 0 victory, 1 game over, 2 invalid arguments, 3 still in game.";
+
+pub const EXIT_INVALID_ARGUMENTS: u8 = 2;
+/// Exit code of the headless mode when the ticks ran out before the game was over.
+pub const EXIT_STILL_IN_GAME: u8 = 3;
+
+#[derive(Debug, Default)]
+pub struct BPCliArgs {
+    pub level: Option<usize>,
+    pub seed: Option<u64>,
+    pub window_size: Option<UVec2>,
+    pub state: BPGameState,
+    pub debug: bool,
+    pub physics_debug: bool,
+    pub replay: Option<PathBuf>,
+    pub headless_ticks: Option<usize>,
+    pub help: bool,
+}
+
+impl BPCliArgs {
+    /// Whether `BPDebugPlugin` is added. Always the case in debug builds and with the `debug` feature.
+    pub fn debug_tools(&self) -> bool {
+        cfg!(debug_assertions)
+            || cfg!(feature = "debug")
+            || self.debug
+            || self.physics_debug
+            || self.state == BPGameState::Editor
+    }
+
+    /// Parses the arguments, without the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut cli = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("Missing value for {arg}"));
+
+            match arg.as_str() {
+                "--level" => cli.level = Some(parse_number(&value()?)?),
+                "--seed" => cli.seed = Some(parse_number(&value()?)?),
+                "--window-size" => cli.window_size = Some(parse_size(&value()?)?),
+                "--state" => cli.state = parse_state(&value()?)?,
+                "--debug" => cli.debug = true,
+                "--physics-debug" => cli.physics_debug = true,
+                "--replay" => cli.replay = Some(value()?.into()),
+                "--headless" => cli.headless_ticks = Some(parse_number(&value()?)?),
+                "--help" | "-h" => cli.help = true,
+                _ => return Err(format!("Unknown argument {arg}")),
+            }
+        }
+
+        Ok(cli)
+    }
+}
+
+fn parse_number<T: std::str::FromStr<Err = std::num::ParseIntError>>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|error| format!("Invalid number {value}: {error}"))
+}
+
+fn parse_size(value: &str) -> Result<UVec2, String> {
+    let Some((width, height)) = value.split_once('x') else {
+        return Err(format!("Invalid window size {value}, expected WIDTHxHEIGHT"));
+    };
+
+    Ok(UVec2::new(parse_number(width)?, parse_number(height)?))
+}
+
+fn parse_state(value: &str) -> Result<BPGameState, String> {
+    match value {
+        "in-game" => Ok(BPGameState::InGame),
+        "game-over" => Ok(BPGameState::GameOver),
+        "victory" => Ok(BPGameState::Victory),
+        "editor" => Ok(BPGameState::Editor),
+        _ => Err(format!("Unknown state {value}, expected in-game, game-over, victory or editor")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<BPCliArgs, String> {
+        BPCliArgs::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_every_option() {
+        let cli = parse(&[
+            "--level", "2",
+            "--seed", "42",
+            "--window-size", "1280x720",
+            "--state", "victory",
+            "--debug",
+            "--physics-debug",
+            "--replay", "replays/last.replay.ron",
+            "--headless", "600",
+        ]).unwrap();
+
+        assert_eq!(cli.level, Some(2));
+        assert_eq!(cli.seed, Some(42));
+        assert_eq!(cli.window_size, Some(UVec2::new(1280, 720)));
+        assert_eq!(cli.state, BPGameState::Victory);
+        assert!(cli.debug);
+        assert!(cli.physics_debug);
+        assert_eq!(cli.replay, Some(PathBuf::from("replays/last.replay.ron")));
+        assert_eq!(cli.headless_ticks, Some(600));
+        assert!(!cli.help);
+    }
+
+    #[test]
+    fn parses_every_state() {
+        for (name, state) in [
+            ("in-game", BPGameState::InGame),
+            ("game-over", BPGameState::GameOver),
+            ("victory", BPGameState::Victory),
+            ("editor", BPGameState::Editor),
+        ] {
+            assert_eq!(parse(&["--state", name]).unwrap().state, state);
+            assert!(USAGE.contains(name), "{name} is missing from the usage");
+        }
+    }
+
+    #[test]
+    fn the_editor_needs_the_debug_tools() {
+        assert!(parse(&["--state", "editor"]).unwrap().debug_tools());
+    }
+
+    #[test]
+    fn nothing_given_is_the_default() {
+        let cli = parse(&[]).unwrap();
+
+        assert_eq!(cli.level, None);
+        assert_eq!(cli.state, BPGameState::InGame);
+        assert!(!cli.help);
+    }
+
+    #[test]
+    fn rejects_unknown_arguments() {
+        assert_eq!(parse(&["--fly"]).unwrap_err(), "Unknown argument --fly");
+        assert!(parse(&["--state", "paused"]).is_err());
+    }
+
+    #[test]
+    fn rejects_missing_values() {
+        assert_eq!(parse(&["--level"]).unwrap_err(), "Missing value for --level");
+        assert_eq!(parse(&["--seed", "42", "--replay"]).unwrap_err(), "Missing value for --replay");
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        assert!(parse(&["--level", "first"]).is_err());
+        assert!(parse(&["--window-size", "1280"]).is_err());
+        assert!(parse(&["--window-size", "1280xtall"]).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::physic::{ONE_WAY_PLATFORM_GROUP, PLAYER_GROUP, WORLD_GROUP};
+use crate::player::BPPlayerMarker;
+use crate::state::BPGameState;
+use crate::tuning::BPPlayerTuning;
+
+// Players are either a dynamic body pushed around by the physics, or a kinematic body moved by
+// Rapier's character controller, which handles slopes, steps and sticking to the ground on its own.
+// Both read the same `Velocity`, so running, jumping and the animation work the same way on top of them.
+pub struct BPControllerPlugin;
+
+impl Plugin for BPControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(FixedUpdate, move_kinematic_players
+                .in_set(BPControllerSystems::Move)
+                .before(PhysicsSet::SyncBackend)
+                .run_if(in_state(BPGameState::InGame)))
+            .add_systems(FixedUpdate, read_kinematic_output
+                .in_set(BPControllerSystems::Read)
+                .after(PhysicsSet::Writeback)
+                .run_if(in_state(BPGameState::InGame)));
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BPControllerSystems {
+    /// Turns the `Velocity` of kinematic players into a translation for the character controller.
+    /// Gameplay systems changing the velocity run before it.
+    Move,
+    /// Turns what the character controller did back into a `Velocity`.
+    /// Gameplay systems reading the velocity after the physics step run after it.
+    Read,
+}
+
+#[derive(Component, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum BPPlayerController {
+    #[default]
+    Dynamic,
+    Kinematic,
+}
+
+impl BPPlayerController {
+    pub fn label(self) -> &'static str {
+        match self {
+            BPPlayerController::Dynamic => "Dynamic",
+            BPPlayerController::Kinematic => "Kinematic",
+        }
+    }
+
+    /// The other controller, there are only two to compare.
+    pub fn toggled(self) -> Self {
+        match self {
+            BPPlayerController::Dynamic => BPPlayerController::Kinematic,
+            BPPlayerController::Kinematic => BPPlayerController::Dynamic,
+        }
+    }
+}
+
+/// Switches the physics of a player to `controller`. Only the character controller depends on the tuning.
+pub fn set_player_controller(entity: &mut EntityCommands,
+                             controller: BPPlayerController,
+                             tuning: &BPPlayerTuning,
+                             players_collide: bool) {
+    match controller {
+        BPPlayerController::Dynamic => {
+            entity
+                .insert((controller, RigidBody::Dynamic))
+                .remove::<(KinematicCharacterController, KinematicCharacterControllerOutput)>();
+        }
+        BPPlayerController::Kinematic => {
+            let mut character_controller = KinematicCharacterController {
+                filter_flags: QueryFilterFlags::EXCLUDE_SENSORS,
+                ..default()
+            };
+            apply_character_tuning(&mut character_controller, tuning);
+            character_controller.filter_groups = Some(character_filter_groups(players_collide));
+
+            entity.insert((controller, RigidBody::KinematicPositionBased, character_controller));
+        }
+    }
+}
+
+pub fn apply_character_tuning(character_controller: &mut KinematicCharacterController, tuning: &BPPlayerTuning) {
+    character_controller.max_slope_climb_angle = tuning.max_slope_angle.to_radians();
+    // Slopes the character can't climb are slid down
+    character_controller.min_slope_slide_angle = tuning.max_slope_angle.to_radians();
+    character_controller.autostep = (tuning.step_height > 0.).then_some(CharacterAutostep {
+        max_height: CharacterLength::Absolute(tuning.step_height),
+        min_width: CharacterLength::Absolute(5.),
+        include_dynamic_bodies: false,
+    });
+    character_controller.snap_to_ground = (tuning.snap_to_ground > 0.).then_some(CharacterLength::Absolute(tuning.snap_to_ground));
+}
+
+/// What the character controller stops against. Enemies are left to the physics step,
+/// so touching one raises the same collision events as with a dynamic body.
+pub fn character_filter_groups(players_collide: bool) -> CollisionGroups {
+    let mut filters = WORLD_GROUP | ONE_WAY_PLATFORM_GROUP;
+    if players_collide {
+        filters |= PLAYER_GROUP;
+    }
+
+    CollisionGroups::new(PLAYER_GROUP, filters)
+}
+
+fn move_kinematic_players(mut query: Query<(&mut KinematicCharacterController,
+                                            &mut Velocity,
+                                            &GravityScale,
+                                            &Damping), With<BPPlayerMarker>>,
+                          config_query: Query<&RapierConfiguration>,
+                          time: Res<Time>) {
+    let Ok(config) = config_query.single() else {
+        return;
+    };
+    let delta = time.delta_secs();
+
+    for (mut character_controller, mut velocity, gravity_scale, damping) in &mut query {
+        // Kinematic bodies ignore gravity and damping, they are applied here the way Rapier would
+        velocity.linvel.y += config.gravity.y * gravity_scale.0 * delta;
+        velocity.linvel.y /= 1. + delta * damping.linear_damping;
+
+        character_controller.translation = Some(velocity.linvel * delta);
+    }
+}
+
+fn read_kinematic_output(mut query: Query<(&KinematicCharacterControllerOutput, &mut Velocity), With<BPPlayerMarker>>,
+                         time: Res<Time>) {
+    let delta = time.delta_secs();
+    if delta <= 0. {
+        return;
+    }
+
+    for (output, mut velocity) in &mut query {
+        // Walls and ceilings stop the character, the ground holds it
+        let mut linvel = output.effective_translation / delta;
+        if output.grounded && linvel.y < 0. {
+            linvel.y = 0.;
+        }
+
+        velocity.linvel = linvel;
+    }
+}
@@ -0,0 +1,789 @@
+use bevy::input::gamepad::GamepadConnectionEvent;
+use bevy::prelude::*;
+use bevy::reflect::ReflectRef;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::debug::{BPDebugMarker, DebugAction};
+use crate::editor::{BPEditorAction, BPEditorMarker};
+use crate::options::{toggle_options_menu, BPMenuAction, BPMenuInputMarker};
+use crate::player::{Action, BPPlayerIndex, BPPlayerMarker, MAX_PLAYERS};
+use crate::settings::BPSettings;
+use crate::state::BPMenuState;
+use crate::ui::BPGameOverUiAction;
+
+pub const CONTROLS_PATH: &str = "controls.ron";
+
+pub struct BPControlsPlugin;
+
+impl Plugin for BPControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<BPRebindState>()
+            .init_resource::<BPPlayerGamepads>()
+            .add_systems(Update, (
+                assign_gamepads,
+                sync_input_maps.run_if(resource_changed::<BPControls>),
+                apply_player_gamepads,
+            ).chain())
+            .add_systems(OnEnter(BPMenuState::Controls), setup_controls_ui)
+            .add_systems(OnExit(BPMenuState::Controls), (cleanup_controls_ui, save_controls))
+            .add_systems(Update, (capture_rebind, controls_navigation, update_controls_ui)
+                .chain()
+                // Escape must still cancel a capture instead of leaving the screen
+                .after(toggle_options_menu)
+                .run_if(in_state(BPMenuState::Controls)));
+    }
+
+    fn finish(&self, app: &mut App) {
+        // Input kinds are registered by the InputManagerPlugins, so the file can only be read once they are built
+        app.insert_resource(BPControls::load());
+    }
+}
+
+/// Every input map of the game. Loaded from disk at startup and copied onto entities when they are spawned.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BPControls {
+    /// One input map per player, indexed by `BPPlayerIndex`.
+    pub players: Vec<InputMap<Action>>,
+    pub game_over_ui: InputMap<BPGameOverUiAction>,
+    pub menu: InputMap<BPMenuAction>,
+    pub debug: InputMap<DebugAction>,
+    pub editor: InputMap<BPEditorAction>,
+    /// The only player map of files written before local co-op, moved into `players` when loaded.
+    #[serde(rename = "player", skip_serializing)]
+    legacy_player: InputMap<Action>,
+}
+
+impl Default for BPControls {
+    fn default() -> Self {
+        Self {
+            // Each player gets one half of the keyboard, gamepads are assigned as they connect
+            players: vec![
+                default_player_map(KeyCode::KeyA, KeyCode::KeyD, KeyCode::Space, KeyCode::KeyS, KeyCode::ShiftLeft),
+                default_player_map(KeyCode::ArrowLeft, KeyCode::ArrowRight, KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::ShiftRight),
+            ],
+            game_over_ui: InputMap::default()
+                .with(BPGameOverUiAction::Restart, KeyCode::KeyR)
+                .with(BPGameOverUiAction::Restart, GamepadButton::Start)
+                .with(BPGameOverUiAction::Quit, KeyCode::KeyQ),
+            menu: InputMap::default()
+                .with(BPMenuAction::ToggleOptions, KeyCode::Escape)
+                .with(BPMenuAction::ToggleOptions, GamepadButton::Select)
+                .with(BPMenuAction::Confirm, KeyCode::Enter)
+                .with(BPMenuAction::Confirm, GamepadButton::South)
+                .with(BPMenuAction::Up, GamepadButton::DPadUp)
+                .with(BPMenuAction::Down, GamepadButton::DPadDown)
+                .with(BPMenuAction::Left, GamepadButton::DPadLeft)
+                .with(BPMenuAction::Right, GamepadButton::DPadRight)
+                .with(BPMenuAction::Up, KeyCode::ArrowUp)
+                .with(BPMenuAction::Up, KeyCode::KeyW)
+                .with(BPMenuAction::Down, KeyCode::ArrowDown)
+                .with(BPMenuAction::Down, KeyCode::KeyS)
+                .with(BPMenuAction::Left, KeyCode::ArrowLeft)
+                .with(BPMenuAction::Left, KeyCode::KeyA)
+                .with(BPMenuAction::Right, KeyCode::ArrowRight)
+                .with(BPMenuAction::Right, KeyCode::KeyD),
+            debug: InputMap::default()
+                .with(DebugAction::ToggleInspector, KeyCode::F1)
+                .with(DebugAction::TogglePhysicsLines, KeyCode::F2)
+                .with(DebugAction::ToggleEditor, KeyCode::F3)
+                .with(DebugAction::CycleTuningPreset, KeyCode::F4),
+            editor: InputMap::default()
+                .with(BPEditorAction::Grab, MouseButton::Left)
+                .with(BPEditorAction::Resize, MouseButton::Right)
+                .with(BPEditorAction::Delete, KeyCode::Delete)
+                .with(BPEditorAction::Delete, KeyCode::Backspace)
+                .with(BPEditorAction::PlacePlatform, KeyCode::Digit1)
+                .with(BPEditorAction::PlaceInvisibleWall, KeyCode::Digit2)
+                .with(BPEditorAction::PlaceEnemySpawn, KeyCode::Digit3)
+                .with(BPEditorAction::PlaceOneWayPlatform, KeyCode::Digit4)
+                .with(BPEditorAction::PlacePickup, KeyCode::Digit5)
+                .with(BPEditorAction::CycleAbility, KeyCode::KeyA)
+                .with(BPEditorAction::AddWaypoint, KeyCode::KeyP)
+                .with(BPEditorAction::ClearPath, KeyCode::KeyC)
+                .with(BPEditorAction::ToggleSnap, KeyCode::KeyG)
+                .with(BPEditorAction::Save, ButtonlikeChord::modified(ModifierKey::Control, KeyCode::KeyS)),
+            legacy_player: InputMap::default(),
+        }
+    }
+}
+
+fn default_player_map(left: KeyCode, right: KeyCode, jump: KeyCode, down: KeyCode, dash: KeyCode) -> InputMap<Action> {
+    InputMap::default()
+        .with_axis(Action::Run, VirtualAxis::new(left, right))
+        .with_axis(Action::Run, GamepadControlAxis::LEFT_X.with_deadzone_symmetric(0.15))
+        .with_axis(Action::Run, VirtualAxis::dpad_x())
+        .with(Action::Jump, jump)
+        .with(Action::Jump, GamepadButton::South)
+        .with(Action::Down, down)
+        .with(Action::Down, GamepadButton::DPadDown)
+        .with(Action::Dash, dash)
+        .with(Action::Dash, GamepadButton::West)
+}
+
+impl BPControls {
+    /// Reads the controls from disk, falling back to the defaults if the file is missing or broken.
+    pub fn load() -> Self {
+        let Ok(content) = std::fs::read_to_string(CONTROLS_PATH) else {
+            return Self::default();
+        };
+
+        match ron::from_str::<BPControls>(&content) {
+            Ok(mut controls) => {
+                // Files written before local co-op only know player one, the others keep their defaults
+                if !controls.legacy_player.is_empty() {
+                    let player = std::mem::take(&mut controls.legacy_player);
+                    match controls.players.first_mut() {
+                        Some(first) => *first = player,
+                        None => controls.players.push(player),
+                    }
+                }
+
+                // Older files may know fewer players than the game supports
+                let defaults = Self::default();
+                controls.players.extend(defaults.players.iter().skip(controls.players.len()).cloned());
+
+                // and miss actions added since, which would otherwise have no way to be triggered
+                for (map, default_map) in controls.players.iter_mut().zip(&defaults.players) {
+                    merge_unbound_actions(map, default_map);
+                }
+                merge_unbound_actions(&mut controls.game_over_ui, &defaults.game_over_ui);
+                merge_unbound_actions(&mut controls.menu, &defaults.menu);
+                merge_unbound_actions(&mut controls.debug, &defaults.debug);
+                merge_unbound_actions(&mut controls.editor, &defaults.editor);
+                controls
+            }
+            Err(error) => {
+                warn!("Could not parse {CONTROLS_PATH}, using default controls: {error}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let content = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(content) => content,
+            Err(error) => {
+                error!("Could not serialize controls: {error}");
+                return;
+            }
+        };
+
+        if let Err(error) = std::fs::write(CONTROLS_PATH, content) {
+            error!("Could not write {CONTROLS_PATH}: {error}");
+        }
+    }
+}
+
+/// Adds the default bindings of every action that has no binding in `map`.
+fn merge_unbound_actions<A: Actionlike>(map: &mut InputMap<A>, defaults: &InputMap<A>) {
+    let mut unbound = defaults.clone();
+    for action in defaults.buttonlike_actions().chain(defaults.axislike_actions()) {
+        if map.get(action).is_some_and(|bindings| !bindings.is_empty()) {
+            unbound.clear_action(action);
+        }
+    }
+    map.merge(&unbound);
+}
+
+/// A single physical input that can be assigned to a binding slot.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BPInput {
+    Key(KeyCode),
+    Gamepad(GamepadButton),
+}
+
+impl BPInput {
+    fn from_buttonlike(input: &dyn Buttonlike) -> Option<Self> {
+        let input = input.as_reflect();
+        if let Some(key) = input.downcast_ref::<KeyCode>() {
+            return Some(BPInput::Key(*key));
+        }
+        input.downcast_ref::<GamepadButton>().map(|button| BPInput::Gamepad(*button))
+    }
+
+    fn boxed(self) -> Box<dyn Buttonlike> {
+        match self {
+            BPInput::Key(key) => Box::new(key),
+            BPInput::Gamepad(button) => Box::new(button),
+        }
+    }
+
+    /// A new binding only replaces the existing bindings coming from the same device.
+    fn same_device(self, other: BPInput) -> bool {
+        matches!((self, other), (BPInput::Key(_), BPInput::Key(_)) | (BPInput::Gamepad(_), BPInput::Gamepad(_)))
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            BPInput::Key(key) => format!("{key:?}").trim_start_matches("Key").trim_start_matches("Digit").to_string(),
+            BPInput::Gamepad(button) => format!("Pad {button:?}"),
+        }
+    }
+}
+
+/// How any button binding is shown to the player, including those that can't be rebound from the controls screen.
+pub fn binding_label(binding: &dyn Buttonlike) -> String {
+    if let Some(input) = BPInput::from_buttonlike(binding) {
+        return input.label();
+    }
+
+    let binding = binding.as_reflect();
+    if let Some(button) = binding.downcast_ref::<MouseButton>() {
+        return format!("Mouse {button:?}");
+    }
+    if let Some(modifier) = binding.downcast_ref::<ModifierKey>() {
+        return format!("{modifier:?}");
+    }
+    if let Some(chord) = binding.downcast_ref::<ButtonlikeChord>()
+        && let ReflectRef::TupleStruct(chord) = chord.reflect_ref()
+        && let Some(buttons) = chord.field(0).and_then(|buttons| buttons.try_downcast_ref::<Vec<Box<dyn Buttonlike>>>()) {
+        return buttons.iter()
+            .map(|button| binding_label(button.as_ref()))
+            .collect::<Vec<_>>()
+            .join("+");
+    }
+    format!("{binding:?}")
+}
+
+/// The button bindings of `action` in `map`, joined with `separator`.
+pub fn action_label<A: Actionlike>(map: &InputMap<A>, action: &A, separator: &str) -> String {
+    map.get_buttonlike(action)
+        .map(|bindings| bindings.iter()
+            .map(|binding| binding_label(binding.as_ref()))
+            .collect::<Vec<_>>()
+            .join(separator))
+        .unwrap_or_default()
+}
+
+/// Groups of slots whose inputs must not overlap.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BPBindingContext {
+    Player,
+    GameOverUi,
+    Debug,
+}
+
+impl BPBindingContext {
+    /// The debug actions are read during gameplay, so they can't share inputs with the players either.
+    fn overlaps(self, other: BPBindingContext) -> bool {
+        self == other
+            || matches!((self, other), (BPBindingContext::Player, BPBindingContext::Debug) | (BPBindingContext::Debug, BPBindingContext::Player))
+    }
+}
+
+/// Everything that can be rebound from the controls screen. Player slots carry the player index.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BPBindingSlot {
+    RunLeft(usize),
+    RunRight(usize),
+    Jump(usize),
+    Down(usize),
+    Dash(usize),
+    Restart,
+    Quit,
+    ToggleInspector,
+    TogglePhysicsLines,
+    ToggleEditor,
+    CycleTuningPreset,
+}
+
+impl BPBindingSlot {
+    pub fn all() -> Vec<BPBindingSlot> {
+        let mut slots = Vec::new();
+        for player in 0..MAX_PLAYERS {
+            slots.extend([
+                BPBindingSlot::RunLeft(player),
+                BPBindingSlot::RunRight(player),
+                BPBindingSlot::Jump(player),
+                BPBindingSlot::Down(player),
+                BPBindingSlot::Dash(player),
+            ]);
+        }
+        slots.extend([
+            BPBindingSlot::Restart,
+            BPBindingSlot::Quit,
+            BPBindingSlot::ToggleInspector,
+            BPBindingSlot::TogglePhysicsLines,
+            BPBindingSlot::ToggleEditor,
+            BPBindingSlot::CycleTuningPreset,
+        ]);
+        slots
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            BPBindingSlot::RunLeft(player) => format!("P{} Run left", player + 1),
+            BPBindingSlot::RunRight(player) => format!("P{} Run right", player + 1),
+            BPBindingSlot::Jump(player) => format!("P{} Jump", player + 1),
+            BPBindingSlot::Down(player) => format!("P{} Down", player + 1),
+            BPBindingSlot::Dash(player) => format!("P{} Dash", player + 1),
+            BPBindingSlot::Restart => "Restart".to_string(),
+            BPBindingSlot::Quit => "Quit".to_string(),
+            BPBindingSlot::ToggleInspector => "Toggle inspector".to_string(),
+            BPBindingSlot::TogglePhysicsLines => "Toggle physics lines".to_string(),
+            BPBindingSlot::ToggleEditor => "Toggle level editor".to_string(),
+            BPBindingSlot::CycleTuningPreset => "Next tuning preset".to_string(),
+        }
+    }
+
+    /// All players share a context, so two players can't end up on the same key.
+    pub fn context(self) -> BPBindingContext {
+        match self {
+            BPBindingSlot::RunLeft(_)
+            | BPBindingSlot::RunRight(_)
+            | BPBindingSlot::Jump(_)
+            | BPBindingSlot::Down(_)
+            | BPBindingSlot::Dash(_) => BPBindingContext::Player,
+            BPBindingSlot::Restart | BPBindingSlot::Quit => BPBindingContext::GameOverUi,
+            BPBindingSlot::ToggleInspector
+            | BPBindingSlot::TogglePhysicsLines
+            | BPBindingSlot::ToggleEditor
+            | BPBindingSlot::CycleTuningPreset => BPBindingContext::Debug,
+        }
+    }
+
+    pub fn inputs(self, controls: &BPControls) -> Vec<BPInput> {
+        match self {
+            BPBindingSlot::RunLeft(player) => axis_inputs(&controls.players[player], &Action::Run, false),
+            BPBindingSlot::RunRight(player) => axis_inputs(&controls.players[player], &Action::Run, true),
+            BPBindingSlot::Jump(player) => button_inputs(&controls.players[player], &Action::Jump),
+            BPBindingSlot::Down(player) => button_inputs(&controls.players[player], &Action::Down),
+            BPBindingSlot::Dash(player) => button_inputs(&controls.players[player], &Action::Dash),
+            BPBindingSlot::Restart => button_inputs(&controls.game_over_ui, &BPGameOverUiAction::Restart),
+            BPBindingSlot::Quit => button_inputs(&controls.game_over_ui, &BPGameOverUiAction::Quit),
+            BPBindingSlot::ToggleInspector => button_inputs(&controls.debug, &DebugAction::ToggleInspector),
+            BPBindingSlot::TogglePhysicsLines => button_inputs(&controls.debug, &DebugAction::TogglePhysicsLines),
+            BPBindingSlot::ToggleEditor => button_inputs(&controls.debug, &DebugAction::ToggleEditor),
+            BPBindingSlot::CycleTuningPreset => button_inputs(&controls.debug, &DebugAction::CycleTuningPreset),
+        }
+    }
+
+    /// The inputs bound to this slot, joined with `separator`, as shown to the player.
+    pub fn inputs_label(self, controls: &BPControls, separator: &str) -> String {
+        self.inputs(controls)
+            .into_iter()
+            .map(BPInput::label)
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    fn is_axis_half(self) -> bool {
+        matches!(self, BPBindingSlot::RunLeft(_) | BPBindingSlot::RunRight(_))
+    }
+
+    fn bind(self, controls: &mut BPControls, input: BPInput) {
+        match self {
+            BPBindingSlot::RunLeft(player) => rebind_axis(&mut controls.players[player], &Action::Run, false, input),
+            BPBindingSlot::RunRight(player) => rebind_axis(&mut controls.players[player], &Action::Run, true, input),
+            BPBindingSlot::Jump(player) => rebind_button(&mut controls.players[player], Action::Jump, input),
+            BPBindingSlot::Down(player) => rebind_button(&mut controls.players[player], Action::Down, input),
+            BPBindingSlot::Dash(player) => rebind_button(&mut controls.players[player], Action::Dash, input),
+            BPBindingSlot::Restart => rebind_button(&mut controls.game_over_ui, BPGameOverUiAction::Restart, input),
+            BPBindingSlot::Quit => rebind_button(&mut controls.game_over_ui, BPGameOverUiAction::Quit, input),
+            BPBindingSlot::ToggleInspector => rebind_button(&mut controls.debug, DebugAction::ToggleInspector, input),
+            BPBindingSlot::TogglePhysicsLines => rebind_button(&mut controls.debug, DebugAction::TogglePhysicsLines, input),
+            BPBindingSlot::ToggleEditor => rebind_button(&mut controls.debug, DebugAction::ToggleEditor, input),
+            BPBindingSlot::CycleTuningPreset => rebind_button(&mut controls.debug, DebugAction::CycleTuningPreset, input),
+        }
+    }
+
+    /// Binds `input` to this slot. If another slot of an overlapping context already uses it,
+    /// the two slots swap inputs. When there is nothing to swap with, the rebind is refused.
+    pub fn rebind(self, controls: &mut BPControls, input: BPInput) -> BPRebindResult {
+        let previous = self.inputs(controls).into_iter().find(|current| current.same_device(input));
+
+        let conflict = BPBindingSlot::all().into_iter().find(|other| {
+            *other != self
+                && other.context().overlaps(self.context())
+                && other.inputs(controls).contains(&input)
+        });
+
+        match (conflict, previous) {
+            // Half an axis can only take the place of the same half of an axis on the input's device
+            (None, None) if self.is_axis_half() => BPRebindResult::NoAxis,
+            (None, _) => {
+                self.bind(controls, input);
+                BPRebindResult::Bound
+            }
+            (Some(other), Some(previous)) => {
+                self.bind(controls, input);
+                other.bind(controls, previous);
+                BPRebindResult::Swapped(other)
+            }
+            (Some(other), None) => BPRebindResult::Conflict(other),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BPRebindResult {
+    Bound,
+    /// The input was used by another slot, which received this slot's previous input.
+    Swapped(BPBindingSlot),
+    /// The input is used by another slot and this slot had nothing to give back.
+    Conflict(BPBindingSlot),
+    /// The slot is half of an axis, and no axis of the input's device is bound to put it in.
+    NoAxis,
+}
+
+fn button_inputs<A: Actionlike>(map: &InputMap<A>, action: &A) -> Vec<BPInput> {
+    map.get_buttonlike(action)
+        .map(|bindings| bindings.iter()
+            .filter_map(|binding| BPInput::from_buttonlike(binding.as_ref()))
+            .collect())
+        .unwrap_or_default()
+}
+
+fn rebind_button<A: Actionlike>(map: &mut InputMap<A>, action: A, input: BPInput) {
+    if let Some(bindings) = map.get_buttonlike_mut(&action) {
+        bindings.retain(|binding| {
+            BPInput::from_buttonlike(binding.as_ref()).is_none_or(|current| !current.same_device(input))
+        });
+    }
+
+    map.insert_boxed(action, input.boxed());
+}
+
+fn axis_inputs<A: Actionlike>(map: &InputMap<A>, action: &A, positive: bool) -> Vec<BPInput> {
+    map.get_axislike(action)
+        .map(|bindings| bindings.iter()
+            .filter_map(|binding| binding.as_ref().as_reflect().downcast_ref::<VirtualAxis>())
+            .filter_map(|axis| {
+                let button = if positive { &axis.positive } else { &axis.negative };
+                BPInput::from_buttonlike(button.as_ref())
+            })
+            .collect())
+        .unwrap_or_default()
+}
+
+fn rebind_axis<A: Actionlike>(map: &mut InputMap<A>, action: &A, positive: bool, input: BPInput) {
+    let Some(bindings) = map.get_axislike_mut(action) else {
+        return;
+    };
+
+    for binding in bindings.iter_mut() {
+        let Some(axis) = binding.as_ref().as_reflect().downcast_ref::<VirtualAxis>() else {
+            continue;
+        };
+
+        let side = if positive { &axis.positive } else { &axis.negative };
+        if !BPInput::from_buttonlike(side.as_ref()).is_some_and(|current| current.same_device(input)) {
+            continue;
+        }
+
+        let mut axis = axis.clone();
+        if positive {
+            axis.positive = input.boxed();
+        } else {
+            axis.negative = input.boxed();
+        }
+        *binding = Box::new(axis);
+    }
+}
+
+/// The gamepad driving each player, indexed by `BPPlayerIndex`.
+#[derive(Resource, Default)]
+pub struct BPPlayerGamepads(pub [Option<Entity>; MAX_PLAYERS]);
+
+#[derive(Resource, Default)]
+pub struct BPRebindState {
+    pub selection: usize,
+    pub capturing: bool,
+    pub message: Option<String>,
+}
+
+#[derive(Component)]
+pub struct BPUiControlsMarker;
+
+#[derive(Component)]
+pub struct BPControlsRow(pub BPBindingSlot);
+
+#[derive(Component)]
+pub struct BPControlsMessage;
+
+fn save_controls(controls: Res<BPControls>){
+    controls.save();
+}
+
+/// Keeps already spawned entities in sync with the controls, so a rebind takes effect immediately.
+fn sync_input_maps(controls: Res<BPControls>,
+                   mut player_query: Query<(&mut InputMap<Action>, &BPPlayerIndex), With<BPPlayerMarker>>,
+                   mut menu_query: Query<&mut InputMap<BPMenuAction>, With<BPMenuInputMarker>>,
+                   mut debug_query: Query<&mut InputMap<DebugAction>, With<BPDebugMarker>>,
+                   mut editor_query: Query<&mut InputMap<BPEditorAction>, With<BPEditorMarker>>){
+    for (mut input_map, index) in &mut player_query {
+        *input_map = controls.players[index.0].clone();
+    }
+    for mut input_map in &mut menu_query {
+        *input_map = controls.menu.clone();
+    }
+    for mut input_map in &mut debug_query {
+        *input_map = controls.debug.clone();
+    }
+    for mut input_map in &mut editor_query {
+        *input_map = controls.editor.clone();
+    }
+}
+
+/// A new gamepad goes to the first player in the game without one. When everyone has a gamepad,
+/// it takes over player one so plugging in a controller always has an effect.
+fn assign_gamepads(mut connection_events: MessageReader<GamepadConnectionEvent>,
+                   settings: Res<BPSettings>,
+                   mut player_gamepads: ResMut<BPPlayerGamepads>){
+    let player_count = settings.player_count.clamp(1, MAX_PLAYERS);
+    for event in connection_events.read() {
+        if event.connected() {
+            if player_gamepads.0.contains(&Some(event.gamepad)) {
+                continue;
+            }
+
+            let slot = player_gamepads.0[..player_count].iter().position(Option::is_none).unwrap_or(0);
+            player_gamepads.0[slot] = Some(event.gamepad);
+        } else {
+            for slot in player_gamepads.0.iter_mut() {
+                if *slot == Some(event.gamepad) {
+                    *slot = None;
+                }
+            }
+        }
+    }
+}
+
+fn apply_player_gamepads(player_gamepads: Res<BPPlayerGamepads>,
+                         mut player_query: Query<(&mut InputMap<Action>, &BPPlayerIndex), With<BPPlayerMarker>>){
+    for (mut input_map, index) in &mut player_query {
+        // Without a gamepad of its own, a player would read from any gamepad, including the other player's
+        let gamepad = player_gamepads.0[index.0].unwrap_or(Entity::PLACEHOLDER);
+        if input_map.gamepad() != Some(gamepad) {
+            input_map.set_gamepad(gamepad);
+        }
+    }
+}
+
+fn setup_controls_ui(mut commands: Commands,
+                     mut rebind_state: ResMut<BPRebindState>){
+    *rebind_state = BPRebindState::default();
+
+    commands.spawn((
+        BPUiControlsMarker,
+        Node {
+            // Take up the whole screen
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(10.0),
+            ..default()
+        },
+        BackgroundColor(Color::BLACK.with_alpha(0.8)),
+        ZIndex(3),
+    )).with_children(|parent| {
+        parent.spawn((
+            Text::new("CONTROLS"),
+            TextFont::from_font_size(60.0),
+            TextColor(Color::WHITE),
+        ));
+
+        for slot in BPBindingSlot::all() {
+            parent.spawn((
+                BPControlsRow(slot),
+                Text::new(""),
+                TextFont::from_font_size(30.0),
+                TextColor(Color::WHITE),
+            ));
+        }
+
+        parent.spawn((
+            BPControlsMessage,
+            Text::new(""),
+            TextFont::from_font_size(20.0),
+            TextColor(Color::srgb(1.0, 0.8, 0.0)),
+        ));
+
+        parent.spawn((
+            Text::new("Up/Down to select, Enter to rebind, Escape to go back"),
+            TextFont::from_font_size(20.0),
+            TextColor(Color::srgb(0.6, 0.6, 0.6)),
+        ));
+    });
+}
+
+fn cleanup_controls_ui(mut commands: Commands,
+                       query: Query<Entity, With<BPUiControlsMarker>>){
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn capture_rebind(mut rebind_state: ResMut<BPRebindState>,
+                  mut controls: ResMut<BPControls>,
+                  keyboard: Res<ButtonInput<KeyCode>>,
+                  gamepads: Query<&Gamepad>){
+    if !rebind_state.capturing {
+        return;
+    }
+
+    // Escape is reserved to cancel the capture
+    if keyboard.just_pressed(KeyCode::Escape) {
+        rebind_state.capturing = false;
+        rebind_state.message = Some("Rebind cancelled".to_string());
+        return;
+    }
+
+    let input = keyboard.get_just_pressed().next().map(|key| BPInput::Key(*key))
+        .or_else(|| gamepads.iter()
+            .find_map(|gamepad| gamepad.get_just_pressed().next())
+            .map(|button| BPInput::Gamepad(*button)));
+
+    let Some(input) = input else {
+        return;
+    };
+
+    let slot = BPBindingSlot::all()[rebind_state.selection];
+    rebind_state.capturing = false;
+    rebind_state.message = Some(match slot.rebind(&mut controls, input) {
+        BPRebindResult::Bound => format!("{} bound to {}", slot.label(), input.label()),
+        BPRebindResult::Swapped(other) => format!("{} was used by {}, the bindings were swapped", input.label(), other.label()),
+        BPRebindResult::Conflict(other) => format!("{} is already used by {}", input.label(), other.label()),
+        BPRebindResult::NoAxis => format!("{} has no axis on the device of {}", slot.label(), input.label()),
+    });
+}
+
+fn controls_navigation(query: Query<&ActionState<BPMenuAction>, With<BPMenuInputMarker>>,
+                       mut rebind_state: ResMut<BPRebindState>){
+    let Ok(action_state) = query.single() else {
+        return;
+    };
+
+    if rebind_state.capturing {
+        return;
+    }
+
+    let count = BPBindingSlot::all().len();
+    if action_state.just_pressed(&BPMenuAction::Up) {
+        rebind_state.selection = (rebind_state.selection + count - 1) % count;
+    }
+    if action_state.just_pressed(&BPMenuAction::Down) {
+        rebind_state.selection = (rebind_state.selection + 1) % count;
+    }
+
+    if action_state.just_pressed(&BPMenuAction::Confirm) {
+        rebind_state.capturing = true;
+        rebind_state.message = Some("Press a key or a gamepad button, Escape to cancel".to_string());
+    }
+}
+
+fn update_controls_ui(mut row_query: Query<(&BPControlsRow, &mut Text, &mut TextColor), Without<BPControlsMessage>>,
+                      mut message_query: Query<&mut Text, With<BPControlsMessage>>,
+                      rebind_state: Res<BPRebindState>,
+                      controls: Res<BPControls>){
+    let selected = BPBindingSlot::all()[rebind_state.selection];
+
+    for (row, mut text, mut color) in &mut row_query {
+        let inputs = row.0.inputs_label(&controls, ", ");
+        let label = if selected == row.0 && rebind_state.capturing {
+            format!("{}: ...", row.0.label())
+        } else {
+            format!("{}: {}", row.0.label(), inputs)
+        };
+
+        if selected == row.0 {
+            text.0 = format!("> {label} <");
+            color.0 = Color::srgb(1.0, 0.8, 0.0);
+        } else {
+            text.0 = label;
+            color.0 = Color::WHITE;
+        }
+    }
+
+    if let Ok(mut text) = message_query.single_mut() {
+        text.0 = rebind_state.message.clone().unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binds_a_free_input() {
+        let mut controls = BPControls::default();
+
+        assert_eq!(BPBindingSlot::Jump(0).rebind(&mut controls, BPInput::Key(KeyCode::KeyK)), BPRebindResult::Bound);
+        assert_eq!(BPBindingSlot::Jump(0).inputs(&controls), [BPInput::Gamepad(GamepadButton::South), BPInput::Key(KeyCode::KeyK)]);
+    }
+
+    #[test]
+    fn binds_half_an_axis() {
+        let mut controls = BPControls::default();
+
+        assert_eq!(BPBindingSlot::RunLeft(0).rebind(&mut controls, BPInput::Key(KeyCode::KeyJ)), BPRebindResult::Bound);
+        assert!(BPBindingSlot::RunLeft(0).inputs(&controls).contains(&BPInput::Key(KeyCode::KeyJ)));
+        assert!(!BPBindingSlot::RunLeft(0).inputs(&controls).contains(&BPInput::Key(KeyCode::KeyA)));
+        assert!(BPBindingSlot::RunRight(0).inputs(&controls).contains(&BPInput::Key(KeyCode::KeyD)));
+    }
+
+    #[test]
+    fn swaps_with_a_slot_of_the_same_player() {
+        let mut controls = BPControls::default();
+
+        let result = BPBindingSlot::Jump(0).rebind(&mut controls, BPInput::Key(KeyCode::KeyD));
+
+        assert_eq!(result, BPRebindResult::Swapped(BPBindingSlot::RunRight(0)));
+        assert!(BPBindingSlot::Jump(0).inputs(&controls).contains(&BPInput::Key(KeyCode::KeyD)));
+        assert!(BPBindingSlot::RunRight(0).inputs(&controls).contains(&BPInput::Key(KeyCode::Space)));
+    }
+
+    #[test]
+    fn swaps_with_another_player() {
+        let mut controls = BPControls::default();
+
+        let result = BPBindingSlot::Jump(0).rebind(&mut controls, BPInput::Key(KeyCode::ArrowUp));
+
+        assert_eq!(result, BPRebindResult::Swapped(BPBindingSlot::Jump(1)));
+        assert!(BPBindingSlot::Jump(1).inputs(&controls).contains(&BPInput::Key(KeyCode::Space)));
+    }
+
+    #[test]
+    fn swaps_with_the_debug_tools() {
+        let mut controls = BPControls::default();
+
+        let result = BPBindingSlot::Dash(0).rebind(&mut controls, BPInput::Key(KeyCode::F1));
+
+        assert_eq!(result, BPRebindResult::Swapped(BPBindingSlot::ToggleInspector));
+        assert_eq!(BPBindingSlot::ToggleInspector.inputs(&controls), [BPInput::Key(KeyCode::ShiftLeft)]);
+    }
+
+    #[test]
+    fn shares_inputs_across_contexts_that_are_never_read_together() {
+        let mut controls = BPControls::default();
+
+        assert_eq!(BPBindingSlot::Restart.rebind(&mut controls, BPInput::Key(KeyCode::Space)), BPRebindResult::Bound);
+        assert!(BPBindingSlot::Jump(0).inputs(&controls).contains(&BPInput::Key(KeyCode::Space)));
+    }
+
+    #[test]
+    fn refuses_a_conflict_with_nothing_to_swap() {
+        let mut controls = BPControls::default();
+
+        // Quit has no gamepad button to give back to Restart
+        let result = BPBindingSlot::Quit.rebind(&mut controls, BPInput::Gamepad(GamepadButton::Start));
+
+        assert_eq!(result, BPRebindResult::Conflict(BPBindingSlot::Restart));
+        assert_eq!(BPBindingSlot::Quit.inputs(&controls), [BPInput::Key(KeyCode::KeyQ)]);
+    }
+
+    #[test]
+    fn refuses_half_an_axis_without_an_axis_on_the_device() {
+        let mut controls = BPControls::default();
+        controls.players[0] = InputMap::default().with_axis(Action::Run, VirtualAxis::new(KeyCode::KeyA, KeyCode::KeyD));
+
+        let result = BPBindingSlot::RunLeft(0).rebind(&mut controls, BPInput::Gamepad(GamepadButton::LeftTrigger));
+
+        assert_eq!(result, BPRebindResult::NoAxis);
+        assert_eq!(BPBindingSlot::RunLeft(0).inputs(&controls), [BPInput::Key(KeyCode::KeyA)]);
+    }
+}
@@ -3,26 +3,39 @@ use bevy_inspector_egui::bevy_egui::{EguiContext, EguiPlugin, EguiPrimaryContext
 use bevy_inspector_egui::egui;
 use leafwing_input_manager::prelude::*;
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::controls::BPControls;
+use crate::editor::BPEditorPlugin;
+use crate::rng::BPRng;
+use crate::save::BPSaveData;
+use crate::tuning::{BPPlayerTuning, BPPlayerTuningPresets};
 
-pub struct BPDebugPlugin;
+#[derive(Default)]
+pub struct BPDebugPlugin {
+    /// Whether the physics colliders are drawn from the start.
+    pub show_physics_lines: bool,
+}
 
 impl Plugin for BPDebugPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_plugins(RapierDebugRenderPlugin::default())
+            .add_plugins(RapierDebugRenderPlugin { enabled: self.show_physics_lines, ..default() })
             .add_plugins(EguiPlugin::default())
             .add_plugins(bevy_inspector_egui::DefaultInspectorConfigPlugin)
             .add_plugins(InputManagerPlugin::<DebugAction>::default())
+            .add_plugins(BPEditorPlugin)
             .add_systems(Startup, setup_debug)
-            .add_systems(Update, toggle_rapier_debug_system)
+            .add_systems(Update, (toggle_rapier_debug_system, cycle_tuning_preset_system))
             .add_systems(EguiPrimaryContextPass, toggle_egui_debug_system);
     }
 }
 
-#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect)]
+#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect, Serialize, Deserialize)]
 pub enum DebugAction {
     TogglePhysicsLines,
     ToggleInspector,
+    ToggleEditor,
+    CycleTuningPreset,
 }
 
 #[derive(Component)]
@@ -34,25 +47,32 @@ pub struct BPDebugState {
     pub show_rapier_debug: bool,
 }
 
-fn setup_debug(mut commands: Commands){
-    let input_map = InputMap::default()
-        .with(DebugAction::ToggleInspector, KeyCode::F1)
-        .with(DebugAction::TogglePhysicsLines, KeyCode::F2);
-
+fn setup_debug(mut commands: Commands,
+               controls: Res<BPControls>,
+               rapier_debug: Res<DebugRenderContext>){
     commands.spawn((
-        BPDebugState { show_inspector: false, show_rapier_debug: false },
+        BPDebugState { show_inspector: false, show_rapier_debug: rapier_debug.enabled },
         BPDebugMarker,
-        input_map
+        controls.debug.clone()
     ));
 }
 
 fn toggle_rapier_debug_system(mut query: Query<(&ActionState<DebugAction>, &mut BPDebugState), With<BPDebugMarker>>,
                 mut rapier_debug: ResMut<DebugRenderContext>){
-    if let Ok(mut action) = query.single_mut() {
-        if action.0.just_pressed(&DebugAction::TogglePhysicsLines) {
-            rapier_debug.enabled = !rapier_debug.enabled;
-            action.1.show_rapier_debug = rapier_debug.enabled;
-        }
+    if let Ok(mut action) = query.single_mut()
+        && action.0.just_pressed(&DebugAction::TogglePhysicsLines) {
+        rapier_debug.enabled = !rapier_debug.enabled;
+        action.1.show_rapier_debug = rapier_debug.enabled;
+    }
+}
+
+fn cycle_tuning_preset_system(query: Query<&ActionState<DebugAction>, With<BPDebugMarker>>,
+                              mut presets: ResMut<BPPlayerTuningPresets>,
+                              mut tuning: ResMut<BPPlayerTuning>){
+    if let Ok(action) = query.single()
+        && action.just_pressed(&DebugAction::CycleTuningPreset) {
+        presets.cycle();
+        *tuning = presets.active_tuning();
     }
 }
 
@@ -80,7 +100,25 @@ fn toggle_egui_debug_system(world: &mut World){
         return;
     }
 
+    let seed = world.resource::<BPRng>().seed();
+    let preset = world.resource::<BPPlayerTuningPresets>()
+        .active_preset()
+        .map(|preset| preset.name.clone())
+        .unwrap_or_default();
+
     egui::Window::new("UI").show(egui_context.get_mut(), |ui| {
+        ui.label(format!("Seed: {seed}"));
+
+        // Edits apply to the players right away, switching preset discards them
+        egui::CollapsingHeader::new(format!("Player tuning ({preset})")).default_open(true).show(ui, |ui| {
+            bevy_inspector_egui::bevy_inspector::ui_for_resource::<BPPlayerTuning>(world, ui);
+        });
+
+        // Unlocking or locking abilities here is saved like a pickup
+        egui::CollapsingHeader::new("Save").show(ui, |ui| {
+            bevy_inspector_egui::bevy_inspector::ui_for_resource::<BPSaveData>(world, ui);
+        });
+
         egui::ScrollArea::vertical().show(ui, |ui| {
             // equivalent to `WorldInspectorPlugin`
             bevy_inspector_egui::bevy_inspector::ui_for_world(world, ui);
@@ -0,0 +1,477 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::abilities::{BPAbility, PICKUP_RADIUS};
+use crate::camera::BPCameraMarker;
+use crate::controls::{action_label, BPBindingSlot, BPControls};
+use crate::debug::{BPDebugMarker, DebugAction};
+use crate::hot_reload::BPWatchedFiles;
+use crate::level::{BPCurrentLevel, BPLevelData, BPLevelElementData, BPLevelElementKind};
+use crate::platform::BPPlatformPath;
+use crate::state::{BPGameState, BPMenuState};
+
+// Edits the `BPLevelData` of the current level with the mouse while in `BPGameState::Editor`.
+// Leaving the editor plays the edited level right away, saving writes it to the level file.
+pub struct BPEditorPlugin;
+
+impl Plugin for BPEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_plugins(InputManagerPlugin::<BPEditorAction>::default())
+            .init_resource::<BPEditorState>()
+            .add_systems(Update, toggle_editor.run_if(in_state(BPMenuState::Closed)))
+            .add_systems(OnEnter(BPGameState::Editor), setup_editor)
+            .add_systems(OnExit(BPGameState::Editor), cleanup_editor)
+            .add_systems(Update, (update_cursor, place_element, edit_element, edit_path, cycle_ability, delete_element, toggle_snap, save_level)
+                .chain()
+                .run_if(in_state(BPGameState::Editor).and(in_state(BPMenuState::Closed))))
+            .add_systems(Update, (draw_level, update_editor_ui).run_if(in_state(BPGameState::Editor)));
+    }
+}
+
+/// Size of a grid cell in world units. Positions and corners snap to it.
+pub const GRID_SIZE: f32 = 25.;
+
+#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect, Serialize, Deserialize)]
+pub enum BPEditorAction {
+    /// Selects the element under the cursor and moves it while held.
+    Grab,
+    /// Selects the element under the cursor and drags its nearest corner while held.
+    Resize,
+    Delete,
+    PlacePlatform,
+    PlaceOneWayPlatform,
+    PlaceInvisibleWall,
+    PlaceEnemySpawn,
+    PlacePickup,
+    /// Changes the ability given by the selected pickup.
+    CycleAbility,
+    /// Adds a waypoint under the cursor to the path of the selected platform.
+    AddWaypoint,
+    /// Removes the path of the selected platform, which then stays in place.
+    ClearPath,
+    ToggleSnap,
+    Save,
+}
+
+/// Holds the editor input map. Spawned when entering the editor.
+#[derive(Component)]
+pub struct BPEditorMarker;
+
+#[derive(Component)]
+pub struct BPEditorText;
+
+#[derive(Clone, Copy, Debug)]
+pub enum BPEditorDrag {
+    /// Keeps the cursor on the same spot of the element.
+    Move { offset: Vec2 },
+    /// The corner opposite to the one being dragged stays in place.
+    Resize { anchor: Vec2 },
+}
+
+#[derive(Resource)]
+pub struct BPEditorState {
+    /// Index in `BPLevelData::elements`.
+    pub selected: Option<usize>,
+    pub drag: Option<BPEditorDrag>,
+    pub snap: bool,
+    /// Cursor position in the world, if it is over the window.
+    pub cursor: Option<Vec2>,
+    pub message: Option<String>,
+}
+
+impl Default for BPEditorState {
+    fn default() -> Self {
+        Self {
+            selected: None,
+            drag: None,
+            snap: true,
+            cursor: None,
+            message: None,
+        }
+    }
+}
+
+impl BPEditorState {
+    fn snapped(&self, position: Vec2) -> Vec2 {
+        if self.snap { (position / GRID_SIZE).round() * GRID_SIZE } else { position }
+    }
+}
+
+/// Size of a newly placed element.
+fn default_half_size(kind: BPLevelElementKind) -> Vec2 {
+    match kind {
+        BPLevelElementKind::Platform => Vec2::new(100., 25.),
+        BPLevelElementKind::OneWayPlatform => Vec2::new(100., 10.),
+        BPLevelElementKind::InvisibleWall => Vec2::new(10., 25.),
+        BPLevelElementKind::EnemySpawn => Vec2::splat(25.),
+        BPLevelElementKind::AbilityPickup(_) => Vec2::splat(PICKUP_RADIUS),
+    }
+}
+
+fn element_color(kind: BPLevelElementKind) -> Color {
+    match kind {
+        BPLevelElementKind::Platform => Color::srgb(0.8, 0.8, 0.8),
+        BPLevelElementKind::OneWayPlatform => Color::srgb(0.6, 0.9, 0.6),
+        BPLevelElementKind::InvisibleWall => Color::srgb(0.2, 0.6, 1.0),
+        BPLevelElementKind::EnemySpawn => Color::srgb(1.0, 0.3, 0.2),
+        BPLevelElementKind::AbilityPickup(_) => Color::srgb(0.9, 0.7, 0.1),
+    }
+}
+
+/// The topmost element under `point`, the last placed one wins.
+fn element_at(level_data: &BPLevelData, point: Vec2) -> Option<usize> {
+    level_data.elements.iter().rposition(|element| element.contains(point))
+}
+
+fn toggle_editor(query: Query<&ActionState<DebugAction>, With<BPDebugMarker>>,
+                 game_state: Res<State<BPGameState>>,
+                 mut next_state: ResMut<NextState<BPGameState>>) {
+    let Ok(action_state) = query.single() else {
+        return;
+    };
+
+    if !action_state.just_pressed(&DebugAction::ToggleEditor) {
+        return;
+    }
+
+    // Leaving the editor plays the level as it was edited
+    next_state.set(match game_state.get() {
+        BPGameState::Editor => BPGameState::InGame,
+        _ => BPGameState::Editor,
+    });
+}
+
+fn setup_editor(mut commands: Commands,
+                controls: Res<BPControls>,
+                mut editor: ResMut<BPEditorState>) {
+    editor.selected = None;
+    editor.drag = None;
+    editor.message = None;
+
+    commands.spawn((
+        BPEditorMarker,
+        controls.editor.clone(),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+        ZIndex(2),
+    )).with_children(|parent| {
+        parent.spawn((
+            BPEditorText,
+            Text::new(""),
+            TextFont::from_font_size(18.0),
+            TextColor(Color::WHITE),
+        ));
+    });
+}
+
+fn cleanup_editor(mut commands: Commands,
+                  query: Query<Entity, With<BPEditorMarker>>) {
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn update_cursor(mut editor: ResMut<BPEditorState>,
+                 window_query: Query<&Window, With<PrimaryWindow>>,
+                 camera_query: Query<(&Camera, &GlobalTransform), With<BPCameraMarker>>) {
+    let cursor = window_query.single().ok().and_then(Window::cursor_position);
+    editor.cursor = match (cursor, camera_query.single()) {
+        (Some(cursor), Ok((camera, camera_transform))) => camera.viewport_to_world_2d(camera_transform, cursor).ok(),
+        _ => None,
+    };
+}
+
+fn place_element(query: Query<&ActionState<BPEditorAction>, With<BPEditorMarker>>,
+                 mut editor: ResMut<BPEditorState>,
+                 mut level_data: ResMut<BPLevelData>) {
+    let Ok(action_state) = query.single() else {
+        return;
+    };
+    let Some(cursor) = editor.cursor else {
+        return;
+    };
+
+    let kind = if action_state.just_pressed(&BPEditorAction::PlacePlatform) {
+        BPLevelElementKind::Platform
+    } else if action_state.just_pressed(&BPEditorAction::PlaceOneWayPlatform) {
+        BPLevelElementKind::OneWayPlatform
+    } else if action_state.just_pressed(&BPEditorAction::PlaceInvisibleWall) {
+        BPLevelElementKind::InvisibleWall
+    } else if action_state.just_pressed(&BPEditorAction::PlaceEnemySpawn) {
+        BPLevelElementKind::EnemySpawn
+    } else if action_state.just_pressed(&BPEditorAction::PlacePickup) {
+        BPLevelElementKind::AbilityPickup(BPAbility::DoubleJump)
+    } else {
+        return;
+    };
+
+    level_data.elements.push(BPLevelElementData {
+        kind,
+        position: editor.snapped(cursor),
+        half_size: default_half_size(kind),
+        path: None,
+    });
+    editor.selected = Some(level_data.elements.len() - 1);
+    editor.drag = None;
+}
+
+fn edit_element(query: Query<&ActionState<BPEditorAction>, With<BPEditorMarker>>,
+                mut editor: ResMut<BPEditorState>,
+                mut level_data: ResMut<BPLevelData>) {
+    let Ok(action_state) = query.single() else {
+        return;
+    };
+    let Some(cursor) = editor.cursor else {
+        return;
+    };
+
+    if action_state.just_pressed(&BPEditorAction::Grab) {
+        editor.selected = element_at(&level_data, cursor);
+        editor.drag = editor.selected.map(|index| BPEditorDrag::Move {
+            offset: level_data.elements[index].position - cursor,
+        });
+    } else if action_state.just_pressed(&BPEditorAction::Resize) {
+        editor.selected = element_at(&level_data, cursor);
+        editor.drag = editor.selected.map(|index| {
+            let element = &level_data.elements[index];
+            let side = (cursor - element.position).signum();
+            BPEditorDrag::Resize { anchor: element.position - side * element.half_size }
+        });
+    }
+
+    if !action_state.pressed(&BPEditorAction::Grab) && !action_state.pressed(&BPEditorAction::Resize) {
+        editor.drag = None;
+    }
+
+    let (Some(index), Some(drag)) = (editor.selected, editor.drag) else {
+        return;
+    };
+
+    // The level may have been reloaded from its file in the meantime
+    let Some(mut element) = level_data.elements.get(index).cloned() else {
+        editor.selected = None;
+        editor.drag = None;
+        return;
+    };
+    match drag {
+        BPEditorDrag::Move { offset } => {
+            element.position = editor.snapped(cursor + offset);
+        }
+        BPEditorDrag::Resize { anchor } => {
+            // Spawns and pickups only mark a position
+            if matches!(element.kind, BPLevelElementKind::EnemySpawn | BPLevelElementKind::AbilityPickup(_)) {
+                return;
+            }
+
+            let corner = editor.snapped(cursor);
+            element.position = (anchor + corner) / 2.;
+            element.half_size = ((corner - anchor).abs() / 2.).max(Vec2::splat(GRID_SIZE / 2.));
+        }
+    }
+
+    // Only touch the level when something moved, so it is not flagged as changed every frame
+    if level_data.elements[index] != element {
+        level_data.elements[index] = element;
+    }
+}
+
+fn edit_path(query: Query<&ActionState<BPEditorAction>, With<BPEditorMarker>>,
+             editor: Res<BPEditorState>,
+             mut level_data: ResMut<BPLevelData>) {
+    let Ok(action_state) = query.single() else {
+        return;
+    };
+    let add_waypoint = action_state.just_pressed(&BPEditorAction::AddWaypoint);
+    if !add_waypoint && !action_state.just_pressed(&BPEditorAction::ClearPath) {
+        return;
+    }
+
+    // The level may have been reloaded from its file in the meantime
+    let Some(element) = editor.selected.and_then(|index| level_data.elements.get_mut(index)) else {
+        return;
+    };
+    if !matches!(element.kind, BPLevelElementKind::Platform | BPLevelElementKind::OneWayPlatform) {
+        return;
+    }
+
+    if !add_waypoint {
+        element.path = None;
+    } else if let Some(cursor) = editor.cursor {
+        let waypoint = editor.snapped(cursor) - element.position;
+        element.path.get_or_insert_default().waypoints.push(waypoint);
+    }
+}
+
+fn cycle_ability(query: Query<&ActionState<BPEditorAction>, With<BPEditorMarker>>,
+                 editor: Res<BPEditorState>,
+                 mut level_data: ResMut<BPLevelData>) {
+    let Ok(action_state) = query.single() else {
+        return;
+    };
+    if !action_state.just_pressed(&BPEditorAction::CycleAbility) {
+        return;
+    }
+
+    // The level may have been reloaded from its file in the meantime
+    let Some(element) = editor.selected.and_then(|index| level_data.elements.get_mut(index)) else {
+        return;
+    };
+    if let BPLevelElementKind::AbilityPickup(ability) = element.kind {
+        element.kind = BPLevelElementKind::AbilityPickup(ability.next());
+    }
+}
+
+fn delete_element(query: Query<&ActionState<BPEditorAction>, With<BPEditorMarker>>,
+                  mut editor: ResMut<BPEditorState>,
+                  mut level_data: ResMut<BPLevelData>) {
+    let Ok(action_state) = query.single() else {
+        return;
+    };
+
+    if !action_state.just_pressed(&BPEditorAction::Delete) {
+        return;
+    }
+
+    if let Some(index) = editor.selected.take()
+        && index < level_data.elements.len() {
+        level_data.elements.remove(index);
+        editor.drag = None;
+    }
+}
+
+fn toggle_snap(query: Query<&ActionState<BPEditorAction>, With<BPEditorMarker>>,
+               mut editor: ResMut<BPEditorState>) {
+    if let Ok(action_state) = query.single()
+        && action_state.just_pressed(&BPEditorAction::ToggleSnap) {
+        editor.snap = !editor.snap;
+    }
+}
+
+fn save_level(query: Query<&ActionState<BPEditorAction>, With<BPEditorMarker>>,
+              mut editor: ResMut<BPEditorState>,
+              level_data: Res<BPLevelData>,
+              current_level: Res<BPCurrentLevel>,
+              asset_server: Res<AssetServer>,
+              watched_files: Option<ResMut<BPWatchedFiles>>) {
+    let Ok(action_state) = query.single() else {
+        return;
+    };
+
+    if action_state.just_pressed(&BPEditorAction::Save) {
+        level_data.save(current_level.0);
+        editor.message = Some(format!("Saved {}", BPLevelData::path(current_level.0).display()));
+
+        // A level saved for the first time had no file to watch when the game started
+        if let Some(mut watched_files) = watched_files
+            && watched_files.level.is_none() {
+            watched_files.level = Some(asset_server.load(BPLevelData::asset_path(current_level.0)));
+        }
+    }
+}
+
+fn draw_level(mut gizmos: Gizmos,
+              editor: Res<BPEditorState>,
+              level_data: Res<BPLevelData>) {
+    if editor.snap {
+        gizmos.grid_2d(Isometry2d::IDENTITY, UVec2::new(80, 48), Vec2::splat(GRID_SIZE), Color::srgb(0.15, 0.15, 0.15));
+    }
+
+    for (index, element) in level_data.elements.iter().enumerate() {
+        let color = if editor.selected == Some(index) {
+            Color::srgb(1.0, 0.8, 0.0)
+        } else {
+            element_color(element.kind)
+        };
+
+        match element.kind {
+            BPLevelElementKind::EnemySpawn | BPLevelElementKind::AbilityPickup(_) => {
+                gizmos.circle_2d(element.position, element.half_size.x, color);
+            }
+            _ => {
+                gizmos.rect_2d(element.position, element.half_size * 2., color);
+            }
+        }
+
+        if let Some(path) = &element.path {
+            draw_path(&mut gizmos, element, path, color);
+        }
+    }
+
+    // Players always start around the origin
+    gizmos.cross_2d(Vec2::ZERO, 20., Color::srgb(0.3, 1.0, 0.3));
+}
+
+/// The stops of a moving platform, with its outline on each waypoint.
+fn draw_path(gizmos: &mut Gizmos,
+             element: &BPLevelElementData,
+             path: &BPPlatformPath,
+             color: Color) {
+    let path_color = color.with_alpha(0.4);
+    let mut previous = element.position;
+    for waypoint in &path.waypoints {
+        let position = element.position + *waypoint;
+        gizmos.line_2d(previous, position, path_color);
+        gizmos.rect_2d(position, element.half_size * 2., path_color);
+        previous = position;
+    }
+}
+
+fn update_editor_ui(mut query: Query<&mut Text, With<BPEditorText>>,
+                    controls: Res<BPControls>,
+                    editor: Res<BPEditorState>,
+                    level_data: Res<BPLevelData>,
+                    current_level: Res<BPCurrentLevel>) {
+    let Ok(mut text) = query.single_mut() else {
+        return;
+    };
+
+    let selection = editor.selected
+        .and_then(|index| level_data.elements.get(index))
+        .map(|element| format!(
+            "{} at ({}, {}), {} x {}{}",
+            element.kind.label(),
+            element.position.x,
+            element.position.y,
+            element.half_size.x * 2.,
+            element.half_size.y * 2.,
+            element.path
+                .as_ref()
+                .map(|path| format!(", moving through {} waypoints ({:?})", path.waypoints.len(), path.mode))
+                .unwrap_or_default(),
+        ))
+        .unwrap_or_else(|| "Nothing selected".to_string());
+
+    // Built from the controls so rebinding them in controls.ron shows here
+    let key = |action: BPEditorAction| action_label(&controls.editor, &action, "/");
+    text.0 = format!(
+        "LEVEL EDITOR - level {}\n\
+        {} platform, {} invisible wall, {} enemy spawn, {} one-way platform, {} ability pickup\n\
+        {} drag to move, {} drag to resize, {} to remove\n\
+        {} add a waypoint to the selected platform, {} clear its path, {} change the ability of a pickup\n\
+        {} snap to grid ({}), {} save, {} play\n\n\
+        {selection}\n{}",
+        current_level.0,
+        key(BPEditorAction::PlacePlatform),
+        key(BPEditorAction::PlaceInvisibleWall),
+        key(BPEditorAction::PlaceEnemySpawn),
+        key(BPEditorAction::PlaceOneWayPlatform),
+        key(BPEditorAction::PlacePickup),
+        key(BPEditorAction::Grab),
+        key(BPEditorAction::Resize),
+        key(BPEditorAction::Delete),
+        key(BPEditorAction::AddWaypoint),
+        key(BPEditorAction::ClearPath),
+        key(BPEditorAction::CycleAbility),
+        key(BPEditorAction::ToggleSnap),
+        if editor.snap { "on" } else { "off" },
+        key(BPEditorAction::Save),
+        BPBindingSlot::ToggleEditor.inputs_label(&controls, "/"),
+        editor.message.clone().unwrap_or_default(),
+    );
+}
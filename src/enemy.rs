@@ -1,13 +1,14 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
-use crate::physic::{INVISIBLE_WALL_GROUP, PAWN_GROUP, PLAYER_GROUP, WORLD_GROUP};
+use crate::physic::{INVISIBLE_WALL_GROUP, ONE_WAY_PLATFORM_GROUP, PAWN_GROUP, PLAYER_GROUP, WORLD_GROUP};
+use crate::platform::BPPassenger;
 use crate::utils::BPGameCleanupMarker;
 
 pub struct BPEnemyPlugin;
 
 impl Plugin for BPEnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, enemy_movement_system);
+        app.add_systems(FixedUpdate, enemy_movement_system.before(PhysicsSet::SyncBackend));
     }
 }
 
@@ -23,6 +24,7 @@ pub struct BPEnemyBundle {
     // Markers & Logic
     pub marker: BPEnemyMarker,
     pub direction: BPEnemyDirection,
+    pub passenger: BPPassenger,
 
     // Physics
     pub rigid_body: RigidBody,
@@ -32,32 +34,36 @@ pub struct BPEnemyBundle {
     pub collision_groups: CollisionGroups,
     pub damping: Damping,
 
-    // Visuals
-    pub mesh: Mesh2d,
-    pub material: MeshMaterial2d<ColorMaterial>,
     pub transform: Transform,
 }
 
+impl Default for BPEnemyBundle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BPEnemyBundle {
-    pub fn new(meshes: &mut ResMut<Assets<Mesh>>,
-               materials: &mut ResMut<Assets<ColorMaterial>>) -> Self {
+    pub fn new() -> Self {
+        Self::at(Vec2::new(0., -150.))
+    }
+
+    pub fn at(position: Vec2) -> Self {
 
-        let mut enemy_damping = Damping::default();
-        enemy_damping.linear_damping = 5.;
+        let enemy_damping = Damping { linear_damping: 5., ..default() };
 
         Self {
             direction: BPEnemyDirection(1.),
+            passenger: BPPassenger(25.),
             marker: BPEnemyMarker {},
-            mesh: Mesh2d(meshes.add(Rectangle::new(50., 50.))),
-            material: MeshMaterial2d(materials.add(Color::srgb(8.25, 2.4, 2.1))), // RGB values exceed 1 to achieve a bright color for the bloom effect
-            transform: Transform::from_xyz(0., -150., 2.),
+            transform: Transform::from_translation(position.extend(2.)),
             rigid_body: RigidBody::Dynamic,
             locked_axes: LockedAxes::ROTATION_LOCKED,
             collider: Collider::ball(25.),
             external_force: ExternalForce::default(),
             collision_groups: CollisionGroups::new(
                 PAWN_GROUP,
-                PLAYER_GROUP | WORLD_GROUP | PAWN_GROUP | INVISIBLE_WALL_GROUP,
+                PLAYER_GROUP | WORLD_GROUP | PAWN_GROUP | INVISIBLE_WALL_GROUP | ONE_WAY_PLATFORM_GROUP,
             ),
             damping: enemy_damping
         }
@@ -81,7 +87,6 @@ fn enemy_movement_system(mut query: Query<(&Transform, &mut ExternalForce, &mut
 
         if let Some((_entity, toi)) = rapier_context.cast_ray(ray_pos, ray_dir, max_toi, solid, filter) {
             // If we hit something close, reverse direction
-            let toi: f32 = toi.into();
             if toi < 50.0 {
                 direction.0 *= -1.;
             }
@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use crate::level::BPCurrentLevel;
+use crate::player::{player_color, player_shape, BPGameplayMessage, BPPlayerIndex, BPPlayerMarker};
+use crate::records::BPRecords;
+use crate::replay::BPReplayPlayback;
+use crate::state::BPGameState;
+use crate::utils::BPGameCleanupMarker;
+
+// Records where the first player is on every tick, and plays the best run of the level back as a ghost.
+pub struct BPGhostPlugin;
+
+impl Plugin for BPGhostPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<BPRunRecorder>()
+            .add_systems(OnEnter(BPGameState::InGame), (start_run, spawn_ghost))
+            .add_systems(FixedUpdate, (record_run, move_ghost)
+                .after(PhysicsSet::Writeback)
+                .run_if(in_state(BPGameState::InGame)))
+            // Playing a replay back is not a new run
+            .add_systems(Update, save_best_run.run_if(not(resource_exists::<BPReplayPlayback>)));
+    }
+}
+
+/// The run being played.
+#[derive(Resource, Default)]
+pub struct BPRunRecorder {
+    pub ticks: usize,
+    pub positions: Vec<Vec2>,
+}
+
+#[derive(Component)]
+#[require(BPGameCleanupMarker)]
+pub struct BPGhost {
+    pub positions: Vec<Vec2>,
+    pub tick: usize,
+}
+
+fn start_run(mut recorder: ResMut<BPRunRecorder>) {
+    *recorder = BPRunRecorder::default();
+}
+
+fn spawn_ghost(mut commands: Commands,
+               mut meshes: ResMut<Assets<Mesh>>,
+               mut materials: ResMut<Assets<ColorMaterial>>,
+               records: Res<BPRecords>,
+               current_level: Res<BPCurrentLevel>) {
+    let Some(record) = records.levels.get(&current_level.0) else {
+        return;
+    };
+    let Some(start) = record.ghost.first() else {
+        return;
+    };
+
+    commands.spawn((
+        BPGhost { positions: record.ghost.clone(), tick: 0 },
+        Mesh2d(meshes.add(player_shape())),
+        MeshMaterial2d(materials.add(player_color(0).with_alpha(0.2))),
+        // Behind the players
+        Transform::from_xyz(start.x, start.y, 1.),
+    ));
+}
+
+fn record_run(mut recorder: ResMut<BPRunRecorder>,
+              player_query: Query<(&BPPlayerIndex, &Transform), With<BPPlayerMarker>>) {
+    let position = player_query
+        .iter()
+        .find(|(index, _)| index.0 == 0)
+        .map(|(_, transform)| transform.translation.xy());
+
+    // In co-op the run goes on after the first player died, the ghost then stays where they fell
+    let Some(position) = position.or(recorder.positions.last().copied()) else {
+        return;
+    };
+
+    recorder.ticks += 1;
+    recorder.positions.push(position);
+}
+
+fn move_ghost(mut ghost_query: Query<(&mut BPGhost, &mut Transform)>) {
+    for (mut ghost, mut transform) in &mut ghost_query {
+        let Some(position) = ghost.positions.get(ghost.tick).copied() else {
+            continue;
+        };
+
+        transform.translation = position.extend(transform.translation.z);
+        ghost.tick += 1;
+    }
+}
+
+fn save_best_run(mut gameplay_messages: MessageReader<BPGameplayMessage>,
+                 mut records: ResMut<BPRecords>,
+                 recorder: Res<BPRunRecorder>,
+                 current_level: Res<BPCurrentLevel>) {
+    let victory = gameplay_messages
+        .read()
+        .any(|message| matches!(message, BPGameplayMessage::Victory));
+
+    if victory && records.submit(current_level.0, recorder.ticks, recorder.positions.clone()) {
+        info!("New best time on level {}: {} ticks", current_level.0, recorder.ticks);
+        records.save();
+    }
+}
@@ -0,0 +1,67 @@
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use crate::abilities::BPAbilitiesPlugin;
+use crate::camera::BPCameraPlugin;
+use crate::controller::BPControllerPlugin;
+use crate::controls::BPControls;
+use crate::enemy::BPEnemyPlugin;
+use crate::input::{BPInputSystems, BPPlayerInput};
+use crate::level::{BPLevelData, BPLevelPlugin};
+use crate::physic::BPPhysicsPlugin;
+use crate::platform::BPPlatformPlugin;
+use crate::player::{BPPlayerIndex, BPPlayerPlugin, MAX_PLAYERS};
+use crate::tuning::{BPPlayerTuningPresets, BPTuningPlugin};
+use crate::rng::{BPRng, BPRngPlugin};
+use crate::settings::BPSettingsPlugin;
+use crate::state::{BPGameState, BPStatePlugin};
+
+/// Runs the simulation without a window, renderer or presentation, one fixed tick per `App::update`.
+/// Nothing is read from or written to disk, and player input comes from `BPScriptedInput`.
+/// The default level and tuning are used unless a `BPLevelData` or `BPPlayerTuningPresets` is inserted before the plugin.
+/// The app is stepped with `App::update`, so call `App::finish` and `App::cleanup` first, as `App::run` would.
+#[derive(Default)]
+pub struct BPHeadlessPlugin {
+    pub seed: u64,
+    pub initial_state: BPGameState,
+}
+
+impl Plugin for BPHeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        let timestep = Time::<Fixed>::default().timestep();
+
+        app
+            .add_plugins((MinimalPlugins, StatesPlugin, AssetPlugin::default(), InputPlugin, TransformPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(timestep))
+            .insert_resource(BPControls::default())
+            .insert_resource(BPRng::new(self.seed))
+            .init_resource::<BPLevelData>()
+            .init_resource::<BPPlayerTuningPresets>()
+            .add_plugins(BPPhysicsPlugin)
+            .add_plugins(BPSettingsPlugin)
+            .add_plugins(BPPlayerPlugin)
+            .add_plugins(BPTuningPlugin)
+            .add_plugins(BPControllerPlugin)
+            .add_plugins(BPPlatformPlugin)
+            .add_plugins(BPAbilitiesPlugin)
+            .add_plugins(BPCameraPlugin)
+            .add_plugins(BPEnemyPlugin)
+            .add_plugins(BPLevelPlugin)
+            .add_plugins(BPRngPlugin)
+            .add_plugins(BPStatePlugin { initial_state: self.initial_state })
+            .init_resource::<BPScriptedInput>()
+            .add_systems(FixedPreUpdate, apply_scripted_input.in_set(BPInputSystems::Sample));
+    }
+}
+
+/// Input of each player by `BPPlayerIndex`, applied on every tick in place of the devices.
+#[derive(Resource, Default)]
+pub struct BPScriptedInput(pub [BPPlayerInput; MAX_PLAYERS]);
+
+fn apply_scripted_input(scripted_input: Res<BPScriptedInput>,
+                        mut player_query: Query<(&BPPlayerIndex, &mut BPPlayerInput)>) {
+    for (index, mut input) in &mut player_query {
+        *input = scripted_input.0[index.0];
+    }
+}
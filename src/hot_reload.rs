@@ -0,0 +1,148 @@
+use std::marker::PhantomData;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use crate::level::{spawn_level_element, BPCurrentLevel, BPLevelData, BPLevelElement, BPLevelElementKind};
+use crate::tuning::{BPPlayerTuning, BPPlayerTuningPresets, PLAYER_TUNING_PATH};
+use crate::state::BPGameState;
+
+/// Where Bevy's `AssetPlugin` reads from by default.
+pub const ASSETS_DIRECTORY: &str = "assets";
+
+// Levels and tuning are read from disk when the app is built, so a run starts the same way every time.
+// They are also loaded as assets: when their file changes, the new content replaces the resources
+// and the level is rebuilt in place. Bevy only watches the files with the `hot_reload` feature.
+pub struct BPHotReloadPlugin;
+
+impl Plugin for BPHotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<BPLevelData>()
+            .init_asset::<BPPlayerTuningPresets>()
+            .register_asset_loader(BPRonLoader::<BPLevelData>::new(&["level.ron"]))
+            .register_asset_loader(BPRonLoader::<BPPlayerTuningPresets>::new(&["presets.ron"]))
+            .add_systems(Startup, watch_files)
+            .add_systems(Update, (reload_level, reload_player_tuning));
+    }
+}
+
+/// Loads any asset stored as RON with the given extensions.
+pub struct BPRonLoader<A> {
+    extensions: &'static [&'static str],
+    marker: PhantomData<fn() -> A>,
+}
+
+impl<A> BPRonLoader<A> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self { extensions, marker: PhantomData }
+    }
+}
+
+impl<A: Asset + DeserializeOwned> AssetLoader for BPRonLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = BevyError;
+
+    async fn load(&self,
+                  reader: &mut dyn Reader,
+                  _settings: &(),
+                  _load_context: &mut LoadContext<'_>) -> Result<A, BevyError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
+
+/// Keeps the watched assets alive.
+#[derive(Resource)]
+pub struct BPWatchedFiles {
+    pub level: Option<Handle<BPLevelData>>,
+    pub player_tuning: Option<Handle<BPPlayerTuningPresets>>,
+}
+
+fn watch_files(mut commands: Commands,
+               asset_server: Res<AssetServer>,
+               current_level: Res<BPCurrentLevel>) {
+    // Without a file the built-in defaults are used, there is nothing to watch
+    let level = BPLevelData::path(current_level.0)
+        .exists()
+        .then(|| asset_server.load(BPLevelData::asset_path(current_level.0)));
+    let player_tuning = std::path::Path::new(ASSETS_DIRECTORY)
+        .join(PLAYER_TUNING_PATH)
+        .exists()
+        .then(|| asset_server.load(PLAYER_TUNING_PATH));
+
+    commands.insert_resource(BPWatchedFiles { level, player_tuning });
+}
+
+/// The content of `handle` if its file just changed.
+fn modified<'a, A: Asset>(events: &mut MessageReader<AssetEvent<A>>,
+                          handle: Option<&Handle<A>>,
+                          assets: &'a Assets<A>) -> Option<&'a A> {
+    let handle = handle?;
+    let mut modified = false;
+    for event in events.read() {
+        modified |= event.is_modified(handle);
+    }
+
+    if modified { assets.get(handle) } else { None }
+}
+
+fn reload_level(mut commands: Commands,
+                mut events: MessageReader<AssetEvent<BPLevelData>>,
+                watched_files: Res<BPWatchedFiles>,
+                assets: Res<Assets<BPLevelData>>,
+                mut level_data: ResMut<BPLevelData>,
+                game_state: Res<State<BPGameState>>,
+                element_query: Query<Entity, With<BPLevelElement>>) {
+    let Some(new_level_data) = modified(&mut events, watched_files.level.as_ref(), &assets) else {
+        return;
+    };
+
+    // Saving from the editor writes what is already there
+    if *level_data == *new_level_data {
+        return;
+    }
+
+    *level_data = new_level_data.clone();
+    info!("Level reloaded");
+
+    if *game_state.get() != BPGameState::InGame {
+        return;
+    }
+
+    for entity in &element_query {
+        commands.entity(entity).despawn();
+    }
+
+    // Enemies keep going, moved spawns apply on the next run
+    for element in &level_data.elements {
+        if element.kind != BPLevelElementKind::EnemySpawn {
+            spawn_level_element(&mut commands, element);
+        }
+    }
+}
+
+fn reload_player_tuning(mut events: MessageReader<AssetEvent<BPPlayerTuningPresets>>,
+                        watched_files: Res<BPWatchedFiles>,
+                        assets: Res<Assets<BPPlayerTuningPresets>>,
+                        mut presets: ResMut<BPPlayerTuningPresets>,
+                        mut tuning: ResMut<BPPlayerTuning>) {
+    let Some(new_presets) = modified(&mut events, watched_files.player_tuning.as_ref(), &assets) else {
+        return;
+    };
+
+    // Stay on the preset being compared, the file only lists them
+    let active = presets.active;
+    *presets = new_presets.clone();
+    presets.active = active.min(presets.presets.len().saturating_sub(1));
+
+    // Players already spawned pick the change up from the resource
+    *tuning = presets.active_tuning();
+    info!("Player tuning reloaded");
+}
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use leafwing_input_manager::plugin::InputManagerSystem;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::player::Action;
+use crate::state::BPMenuState;
+
+// `ActionState` is refreshed once per frame, while gameplay runs at a fixed rate.
+// Depending on the frame rate a press could be seen by zero or by several fixed ticks,
+// so presses are latched every frame and handed over to exactly one tick as a snapshot.
+pub struct BPInputPlugin;
+
+impl Plugin for BPInputPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(PreUpdate, latch_player_input
+                .after(InputManagerSystem::Update)
+                .run_if(in_state(BPMenuState::Closed)))
+            .add_systems(FixedPreUpdate, sample_player_input.in_set(BPInputSystems::Sample));
+    }
+}
+
+/// Runs in `FixedPreUpdate` and fills `BPPlayerInput` for the current tick.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BPInputSystems {
+    Sample,
+}
+
+/// Presses seen since the last fixed tick.
+#[derive(Component, Default, Debug)]
+pub struct BPInputLatch {
+    pub jump_pressed: bool,
+    pub dash_pressed: bool,
+    pub down_pressed: bool,
+}
+
+/// Input of one player for the current fixed tick. Gameplay systems read this instead of `ActionState`.
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BPPlayerInput {
+    pub run: f32,
+    /// True for exactly one tick per press.
+    pub jump_pressed: bool,
+    pub jump_held: bool,
+    /// Replays recorded before dropping through platforms existed never hold it.
+    #[serde(default)]
+    pub down_held: bool,
+    /// True for exactly one tick per press.
+    #[serde(default)]
+    pub dash_pressed: bool,
+    /// True for exactly one tick per press.
+    #[serde(default)]
+    pub down_pressed: bool,
+}
+
+fn latch_player_input(mut query: Query<(&ActionState<Action>, &mut BPInputLatch)>) {
+    for (action_state, mut latch) in &mut query {
+        if action_state.just_pressed(&Action::Jump) {
+            latch.jump_pressed = true;
+        }
+        if action_state.just_pressed(&Action::Dash) {
+            latch.dash_pressed = true;
+        }
+        if action_state.just_pressed(&Action::Down) {
+            latch.down_pressed = true;
+        }
+    }
+}
+
+fn sample_player_input(mut query: Query<(&ActionState<Action>, &mut BPInputLatch, &mut BPPlayerInput)>) {
+    for (action_state, mut latch, mut input) in &mut query {
+        *input = BPPlayerInput {
+            run: action_state.value(&Action::Run),
+            jump_pressed: latch.jump_pressed,
+            jump_held: action_state.pressed(&Action::Jump),
+            down_held: action_state.pressed(&Action::Down),
+            dash_pressed: latch.dash_pressed,
+            down_pressed: latch.down_pressed,
+        };
+
+        latch.jump_pressed = false;
+        latch.dash_pressed = false;
+        latch.down_pressed = false;
+    }
+}
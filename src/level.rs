@@ -0,0 +1,303 @@
+use std::path::PathBuf;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::abilities::{BPAbility, BPAbilityPickup, PICKUP_RADIUS};
+use crate::controller::set_player_controller;
+use crate::controls::BPControls;
+use crate::enemy::BPEnemyBundle;
+use crate::hot_reload::ASSETS_DIRECTORY;
+use crate::physic::{INVISIBLE_WALL_GROUP, ONE_WAY_PLATFORM_GROUP, PAWN_GROUP, PLAYER_GROUP, WORLD_GROUP};
+use crate::platform::{BPMovingPlatform, BPOneWayPlatform, BPPlatformPath};
+use crate::player::{BPPlayerBundle, MAX_PLAYERS};
+use crate::replay::BPReplayPlayback;
+use crate::save::BPSaveData;
+use crate::settings::BPSettings;
+use crate::state::BPGameState;
+use crate::tuning::BPPlayerTuning;
+use crate::utils::BPGameCleanupMarker;
+
+/// Levels are stored as `level_<index>.level.ron` in this directory of the assets.
+pub const LEVELS_DIRECTORY: &str = "levels";
+
+pub struct BPLevelPlugin;
+
+impl Plugin for BPLevelPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<BPLevelData>()
+            .init_resource::<BPCurrentLevel>()
+            .add_systems(OnEnter(BPGameState::InGame), setup_level)
+            .add_systems(OnExit(BPGameState::InGame), cleanup_level);
+    }
+
+    fn finish(&self, app: &mut App) {
+        // The level is entered before `Startup`, so it has to be read while building the app.
+        // Whoever builds the app can provide it instead, like the headless simulation does.
+        if !app.world().contains_resource::<BPLevelData>() {
+            let current_level = app.world().resource::<BPCurrentLevel>().0;
+            app.insert_resource(BPLevelData::load(current_level));
+        }
+    }
+}
+
+/// Static part of a level: ground, walls and sensors. Despawned with the rest of the game.
+#[derive(Component)]
+#[require(BPGameCleanupMarker)]
+pub struct BPLevelElement;
+
+/// Index of the level being played, starting at 0.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BPCurrentLevel(pub usize);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum BPLevelElementKind {
+    Platform,
+    /// Platform players jump up through and land on.
+    OneWayPlatform,
+    /// Sensor that turns enemies around, players go through it.
+    InvisibleWall,
+    /// Where an enemy appears. Its size is not used.
+    EnemySpawn,
+    /// Unlocks an ability when a player touches it. Its size is not used.
+    AbilityPickup(BPAbility),
+}
+
+impl BPLevelElementKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            BPLevelElementKind::Platform => "Platform",
+            BPLevelElementKind::OneWayPlatform => "One-way platform",
+            BPLevelElementKind::InvisibleWall => "Invisible wall",
+            BPLevelElementKind::EnemySpawn => "Enemy spawn",
+            BPLevelElementKind::AbilityPickup(BPAbility::DoubleJump) => "Double jump pickup",
+            BPLevelElementKind::AbilityPickup(BPAbility::Dash) => "Dash pickup",
+            BPLevelElementKind::AbilityPickup(BPAbility::WallJump) => "Wall jump pickup",
+            BPLevelElementKind::AbilityPickup(BPAbility::GroundPound) => "Ground pound pickup",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Reflect)]
+pub struct BPLevelElementData {
+    pub kind: BPLevelElementKind,
+    /// Center of the element, in world units.
+    pub position: Vec2,
+    pub half_size: Vec2,
+    /// Makes platforms move along it. Other elements stay in place.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<BPPlatformPath>,
+}
+
+impl BPLevelElementData {
+    pub fn contains(&self, point: Vec2) -> bool {
+        (point - self.position).abs().cmple(self.half_size).all()
+    }
+}
+
+/// Everything a level is made of, as stored in its file. The players always start at the origin.
+/// Also an asset, so changes to the file can be picked up while the game runs.
+#[derive(Resource, Asset, Serialize, Deserialize, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct BPLevelData {
+    pub elements: Vec<BPLevelElementData>,
+}
+
+impl Default for BPLevelData {
+    /// Used when no level can be read from disk and by the headless app: one floor between two
+    /// invisible walls, an enemy, and a pickup for each ability.
+    fn default() -> Self {
+        Self {
+            elements: vec![
+                BPLevelElementData {
+                    kind: BPLevelElementKind::Platform,
+                    position: Vec2::new(0., -200.),
+                    half_size: Vec2::new(750., 25.),
+                    path: None,
+                },
+                BPLevelElementData {
+                    kind: BPLevelElementKind::InvisibleWall,
+                    position: Vec2::new(700., -150.),
+                    half_size: Vec2::new(10., 25.),
+                    path: None,
+                },
+                BPLevelElementData {
+                    kind: BPLevelElementKind::InvisibleWall,
+                    position: Vec2::new(-700., -150.),
+                    half_size: Vec2::new(10., 25.),
+                    path: None,
+                },
+                BPLevelElementData {
+                    kind: BPLevelElementKind::EnemySpawn,
+                    position: Vec2::new(0., -150.),
+                    half_size: Vec2::splat(25.),
+                    path: None,
+                },
+                // The double jump and the dash are within a hop, the others higher up
+                BPLevelElementData {
+                    kind: BPLevelElementKind::AbilityPickup(BPAbility::DoubleJump),
+                    position: Vec2::new(-550., -80.),
+                    half_size: Vec2::splat(PICKUP_RADIUS),
+                    path: None,
+                },
+                BPLevelElementData {
+                    kind: BPLevelElementKind::AbilityPickup(BPAbility::Dash),
+                    position: Vec2::new(550., -80.),
+                    half_size: Vec2::splat(PICKUP_RADIUS),
+                    path: None,
+                },
+                BPLevelElementData {
+                    kind: BPLevelElementKind::AbilityPickup(BPAbility::WallJump),
+                    position: Vec2::new(-250., 75.),
+                    half_size: Vec2::splat(PICKUP_RADIUS),
+                    path: None,
+                },
+                BPLevelElementData {
+                    kind: BPLevelElementKind::AbilityPickup(BPAbility::GroundPound),
+                    position: Vec2::new(250., 75.),
+                    half_size: Vec2::splat(PICKUP_RADIUS),
+                    path: None,
+                },
+            ],
+        }
+    }
+}
+
+impl BPLevelData {
+    /// Path of the level file, relative to the assets.
+    pub fn asset_path(level: usize) -> String {
+        format!("{LEVELS_DIRECTORY}/level_{level}.level.ron")
+    }
+
+    pub fn path(level: usize) -> PathBuf {
+        PathBuf::from(ASSETS_DIRECTORY).join(Self::asset_path(level))
+    }
+
+    /// Reads a level from disk, falling back to the default level if the file is missing or broken.
+    pub fn load(level: usize) -> Self {
+        let path = Self::path(level);
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+
+        match ron::from_str(&content) {
+            Ok(data) => data,
+            Err(error) => {
+                warn!("Could not parse {}, using the default level: {error}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, level: usize) {
+        let path = Self::path(level);
+        let content = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(content) => content,
+            Err(error) => {
+                error!("Could not serialize level: {error}");
+                return;
+            }
+        };
+
+        if let Some(directory) = path.parent()
+            && let Err(error) = std::fs::create_dir_all(directory) {
+            error!("Could not create {}: {error}", directory.display());
+            return;
+        }
+
+        if let Err(error) = std::fs::write(&path, content) {
+            error!("Could not write {}: {error}", path.display());
+        }
+    }
+}
+
+fn setup_level(mut commands: Commands,
+               controls: Res<BPControls>,
+               settings: Res<BPSettings>,
+               tuning: Res<BPPlayerTuning>,
+               save_data: Res<BPSaveData>,
+               level_data: Res<BPLevelData>,
+               playback: Option<Res<BPReplayPlayback>>) {
+    // A replay brings its own players, the settings are left as the player set them
+    let replay = playback.as_ref().map(|playback| &playback.replay);
+    let player_count = replay.map_or(settings.player_count, |replay| replay.player_count);
+    let players_collide = replay.map_or(settings.players_collide, |replay| replay.players_collide);
+    let player_controllers = replay.map_or(settings.player_controllers, |replay| replay.player_controllers);
+    let abilities = replay.map_or(save_data.abilities, |replay| replay.abilities);
+    let tuning = replay.map_or(&*tuning, |replay| &replay.tuning);
+
+    //Spawn the players
+    for (index, controller) in player_controllers.into_iter().enumerate().take(player_count.clamp(1, MAX_PLAYERS)) {
+        let mut player = commands.spawn(BPPlayerBundle::new(index, &controls, tuning, abilities, players_collide));
+        set_player_controller(&mut player, controller, tuning, players_collide);
+    }
+
+    for element in &level_data.elements {
+        spawn_level_element(&mut commands, element);
+    }
+}
+
+pub fn spawn_level_element(commands: &mut Commands, element: &BPLevelElementData) {
+    let transform = Transform::from_translation(element.position.extend(0.));
+    let collider = Collider::cuboid(element.half_size.x, element.half_size.y);
+
+    match element.kind {
+        BPLevelElementKind::Platform => {
+            let mut platform = commands.spawn((
+                BPLevelElement,
+                transform,
+                RigidBody::Fixed,
+                collider,
+                CollisionGroups::new(WORLD_GROUP, WORLD_GROUP | PLAYER_GROUP | PAWN_GROUP),
+            ));
+            insert_platform_path(&mut platform, element);
+        }
+        BPLevelElementKind::OneWayPlatform => {
+            let mut platform = commands.spawn((
+                BPLevelElement,
+                BPOneWayPlatform { half_size: element.half_size },
+                transform,
+                RigidBody::Fixed,
+                collider,
+                CollisionGroups::new(ONE_WAY_PLATFORM_GROUP, PLAYER_GROUP | PAWN_GROUP),
+            ));
+            insert_platform_path(&mut platform, element);
+        }
+        BPLevelElementKind::InvisibleWall => {
+            commands.spawn((
+                BPLevelElement,
+                transform,
+                Sensor,
+                collider,
+                CollisionGroups::new(INVISIBLE_WALL_GROUP, WORLD_GROUP | PLAYER_GROUP | PAWN_GROUP),
+            ));
+        }
+        BPLevelElementKind::EnemySpawn => {
+            commands.spawn(BPEnemyBundle::at(element.position));
+        }
+        BPLevelElementKind::AbilityPickup(ability) => {
+            commands.spawn((
+                BPLevelElement,
+                BPAbilityPickup(ability),
+                transform,
+            ));
+        }
+    }
+}
+
+/// Platforms with a path become kinematic bodies moved by `BPPlatformPlugin`.
+fn insert_platform_path(platform: &mut EntityCommands, element: &BPLevelElementData) {
+    if let Some(path) = &element.path {
+        platform.insert((
+            RigidBody::KinematicPositionBased,
+            BPMovingPlatform::new(element.position, element.half_size, path),
+        ));
+    }
+}
+
+fn cleanup_level(mut commands: Commands, cleanup_query: Query<Entity, With<BPGameCleanupMarker>>) {
+    for entity in &cleanup_query {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,92 @@
+pub mod player;
+pub mod camera;
+pub mod debug;
+pub mod enemy;
+pub mod physic;
+pub mod state;
+pub mod ui;
+pub mod utils;
+pub mod particle;
+pub mod settings;
+pub mod options;
+pub mod controls;
+pub mod audio;
+pub mod music;
+pub mod level;
+pub mod input;
+pub mod replay;
+pub mod rng;
+pub mod records;
+pub mod ghost;
+pub mod presentation;
+pub mod headless;
+pub mod editor;
+pub mod hot_reload;
+pub mod tuning;
+pub mod controller;
+pub mod platform;
+pub mod abilities;
+pub mod save;
+
+use bevy::app::PluginGroupBuilder;
+use bevy::prelude::*;
+use crate::abilities::BPAbilitiesPlugin;
+use crate::audio::BPAudioPlugin;
+use crate::camera::BPCameraPlugin;
+use crate::controller::BPControllerPlugin;
+use crate::controls::BPControlsPlugin;
+use crate::enemy::BPEnemyPlugin;
+use crate::ghost::BPGhostPlugin;
+use crate::hot_reload::BPHotReloadPlugin;
+use crate::input::BPInputPlugin;
+use crate::level::BPLevelPlugin;
+use crate::music::BPMusicPlugin;
+use crate::options::BPOptionsPlugin;
+use crate::particle::BPParticlePlugin;
+use crate::physic::BPPhysicsPlugin;
+use crate::platform::BPPlatformPlugin;
+use crate::player::BPPlayerPlugin;
+use crate::presentation::BPPresentationPlugin;
+use crate::records::BPRecordsPlugin;
+use crate::replay::BPReplayPlugin;
+use crate::rng::BPRngPlugin;
+use crate::save::BPSavePlugin;
+use crate::settings::BPSettingsPlugin;
+use crate::state::BPStatePlugin;
+use crate::tuning::BPTuningPlugin;
+use crate::ui::BPUIPlugin;
+
+/// The whole game, to be added after `DefaultPlugins`.
+/// Resources inserted before it (`BPSettings`, `BPRng`, `BPCurrentLevel`, ...) are kept.
+pub struct BPGamePlugin;
+
+impl PluginGroup for BPGamePlugin {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(BPSettingsPlugin)
+            .add(BPStatePlugin::default())
+            .add(BPPhysicsPlugin)
+            .add(BPPlayerPlugin)
+            .add(BPTuningPlugin)
+            .add(BPControllerPlugin)
+            .add(BPPlatformPlugin)
+            .add(BPSavePlugin)
+            .add(BPAbilitiesPlugin)
+            .add(BPInputPlugin)
+            .add(BPParticlePlugin)
+            .add(BPCameraPlugin)
+            .add(BPEnemyPlugin)
+            .add(BPUIPlugin)
+            .add(BPOptionsPlugin)
+            .add(BPControlsPlugin)
+            .add(BPAudioPlugin)
+            .add(BPMusicPlugin)
+            .add(BPLevelPlugin)
+            .add(BPReplayPlugin)
+            .add(BPRngPlugin)
+            .add(BPRecordsPlugin)
+            .add(BPGhostPlugin)
+            .add(BPPresentationPlugin)
+            .add(BPHotReloadPlugin)
+    }
+}
@@ -1,96 +1,131 @@
-mod player;
-mod camera;
-mod debug;
-mod enemy;
-mod physic;
-mod state;
-mod ui;
-mod utils;
-mod particle;
+mod cli;
 
+use std::process::ExitCode;
 use bevy::prelude::*;
-use bevy_rapier2d::prelude::*;
-use crate::camera::{BPCameraBundle, BPCameraPlugin};
-use crate::debug::BPDebugPlugin;
-use crate::enemy::{BPEnemyBundle, BPEnemyPlugin};
-use crate::player::{BPPlayerBundle, BPPlayerPlugin};
-use crate::state::BPGameState;
-use crate::ui::BPUIPlugin;
-use crate::utils::BPGameCleanupMarker;
-
-fn main() {
+use bevy_platformer::BPGamePlugin;
+use bevy_platformer::debug::BPDebugPlugin;
+use bevy_platformer::headless::BPHeadlessPlugin;
+use bevy_platformer::level::{BPCurrentLevel, BPLevelData};
+use bevy_platformer::replay::{BPReplayPlayback, BPReplayPlugin};
+use bevy_platformer::rng::BPRng;
+use bevy_platformer::settings::BPSettings;
+use bevy_platformer::state::{BPGameState, BPStatePlugin};
+use bevy_platformer::tuning::BPPlayerTuningPresets;
+use crate::cli::{BPCliArgs, EXIT_INVALID_ARGUMENTS, EXIT_STILL_IN_GAME, USAGE};
+
+fn main() -> ExitCode {
+    let mut cli = match BPCliArgs::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(error) => {
+            eprintln!("{error}\n\n{USAGE}");
+            return ExitCode::from(EXIT_INVALID_ARGUMENTS);
+        }
+    };
+
+    if cli.help {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    let playback = match cli.replay.take().map(BPReplayPlayback::from_file).transpose() {
+        Ok(playback) => playback,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::from(EXIT_INVALID_ARGUMENTS);
+        }
+    };
+
+    if let Some(playback) = &playback {
+        // A replay only reproduces a run if the game is set up the same way
+        cli.seed = Some(playback.replay.seed);
+        cli.level = Some(playback.replay.level);
+    }
+
+    match cli.headless_ticks {
+        Some(ticks) => run_headless(&cli, playback, ticks),
+        None => {
+            run_windowed(&cli, playback);
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+fn run_windowed(cli: &BPCliArgs, playback: Option<BPReplayPlayback>) {
     let mut app = App::new();
 
+    // Settings are read before the window is created so it opens in the right mode
+    let settings = BPSettings::load();
+    let mut window = settings.window();
+    if let Some(size) = cli.window_size {
+        window.resolution = size.into();
+    }
+
+    insert_cli_resources(&mut app, cli);
+
     app
-        .add_plugins(DefaultPlugins)
-        .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
-        .add_plugins(BPPlayerPlugin)
-        .add_plugins(particle::BPParticlePlugin)
-        .add_plugins(BPCameraPlugin)
-        .add_plugins(BPEnemyPlugin)
-        .add_plugins(BPUIPlugin)
-        .add_systems(Startup, setup)
-        .add_systems(OnEnter(BPGameState::InGame), setup_game)
-        .add_systems(OnExit(BPGameState::InGame), cleanup_game);
-
-    app.init_state::<BPGameState>();
-
-    if cfg!(debug_assertions) {
-        app.add_plugins(BPDebugPlugin);
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(window),
+            ..default()
+        }))
+        .insert_resource(settings)
+        .add_plugins(BPGamePlugin.build().set(BPStatePlugin { initial_state: cli.state }));
+
+    if let Some(playback) = playback {
+        app.insert_resource(playback);
+    }
+
+    if cli.debug_tools() {
+        app.add_plugins(BPDebugPlugin { show_physics_lines: cli.physics_debug });
     }
 
     app.run();
 }
 
-#[derive(Component)]
-#[require(BPGameCleanupMarker)]
-struct BPLevelElement;
+/// Runs the simulation until the game is over or `ticks` fixed ticks have passed. Without a replay the players stand still.
+fn run_headless(cli: &BPCliArgs, playback: Option<BPReplayPlayback>, ticks: usize) -> ExitCode {
+    let mut app = App::new();
 
-fn setup(mut commands: Commands) {
-    // Spawn the camera
-    commands.spawn(BPCameraBundle::new(5.0));
-}
+    insert_cli_resources(&mut app, cli);
+    app
+        .insert_resource(BPLevelData::load(cli.level.unwrap_or_default()))
+        .insert_resource(BPPlayerTuningPresets::load());
+
+    app.add_plugins(BPHeadlessPlugin {
+        seed: cli.seed.unwrap_or_default(),
+        initial_state: cli.state,
+    });
+
+    if let Some(playback) = playback {
+        app
+            .insert_resource(playback)
+            .add_plugins(BPReplayPlugin);
+    }
 
-fn setup_game(mut commands: Commands,
-              mut meshes: ResMut<Assets<Mesh>>,
-              mut materials: ResMut<Assets<ColorMaterial>>,) {
-    //Spawn the player
-    commands.spawn(BPPlayerBundle::new(&mut meshes, &mut materials));
-    //Spawn the enemy
-    commands.spawn(BPEnemyBundle::new(&mut meshes, &mut materials));
-
-    // Spawn the ground
-    commands.spawn((
-        BPLevelElement,
-        Mesh2d(meshes.add(Rectangle::new(1500.0, 50.0))),
-        MeshMaterial2d(materials.add(Color::srgb(34.0/255.0, 34.0/255.0, 34.0/255.0))),
-        Transform::from_xyz(0., -200., 0.),
-        RigidBody::Fixed,
-        Collider::cuboid(750., 25.),
-        CollisionGroups::new(physic::WORLD_GROUP, physic::WORLD_GROUP | physic::PLAYER_GROUP | physic::PAWN_GROUP),
-    ));
-
-    //Spawn the invisible wall
-    commands.spawn((
-        BPLevelElement,
-        Transform::from_xyz(700., -150., 0.),
-        Sensor::default(),
-        Collider::cuboid(10., 25.),
-        CollisionGroups::new(physic::INVISIBLE_WALL_GROUP, physic::WORLD_GROUP | physic::PLAYER_GROUP | physic::PAWN_GROUP),
-    ));
-
-    //Spawn the invisible wall
-    commands.spawn((
-        BPLevelElement,
-        Transform::from_xyz(-700., -150., 0.),
-        Sensor::default(),
-        Collider::cuboid(10., 25.),
-        CollisionGroups::new(physic::INVISIBLE_WALL_GROUP, physic::WORLD_GROUP | physic::PLAYER_GROUP | physic::PAWN_GROUP),
-    ));
+    app.finish();
+    app.cleanup();
+
+    // The first update enters the starting state
+    app.update();
+    for _ in 0..ticks {
+        if *app.world().resource::<State<BPGameState>>().get() != BPGameState::InGame {
+            break;
+        }
+        app.update();
+    }
+
+    match app.world().resource::<State<BPGameState>>().get() {
+        BPGameState::Victory => ExitCode::SUCCESS,
+        BPGameState::GameOver => ExitCode::FAILURE,
+        BPGameState::InGame | BPGameState::Editor => ExitCode::from(EXIT_STILL_IN_GAME),
+    }
 }
 
-fn cleanup_game(mut commands: Commands, cleanup_query: Query<Entity, With<BPGameCleanupMarker>>) {
-    for entity in &cleanup_query {
-        commands.entity(entity).despawn();
+fn insert_cli_resources(app: &mut App, cli: &BPCliArgs) {
+    if let Some(level) = cli.level {
+        app.insert_resource(BPCurrentLevel(level));
     }
-}
\ No newline at end of file
+
+    if let Some(seed) = cli.seed {
+        app.insert_resource(BPRng::new(seed));
+    }
+}
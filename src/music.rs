@@ -0,0 +1,118 @@
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use crate::audio::BPAudioBus;
+use crate::level::BPCurrentLevel;
+use crate::settings::BPSettings;
+use crate::state::{BPGameState, BPMenuState};
+
+pub struct BPMusicPlugin;
+
+impl Plugin for BPMusicPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<BPMusicDuck>()
+            .add_systems(Update, (select_music_track, update_music_duck, fade_music).chain());
+    }
+}
+
+/// Time it takes for one track to fade out while the next one fades in.
+const CROSSFADE_SECS: f32 = 1.5;
+/// Music volume multiplier while a menu pauses the game.
+const DUCKED_VOLUME: f32 = 0.3;
+
+/// One track per level, the list loops if there are more levels than tracks.
+const LEVEL_TRACKS: [&str; 2] = [
+    "audio/music/level_1.ogg",
+    "audio/music/level_2.ogg",
+];
+
+fn music_track(state: BPGameState, level: BPCurrentLevel) -> &'static str {
+    match state {
+        BPGameState::InGame | BPGameState::Editor => LEVEL_TRACKS[level.0 % LEVEL_TRACKS.len()],
+        BPGameState::GameOver => "audio/music/game_over.ogg",
+        BPGameState::Victory => "audio/music/victory.ogg",
+    }
+}
+
+/// A playing music track. `fade` goes from 0 to 1 when fading in, and back to 0 before the track is despawned.
+#[derive(Component)]
+pub struct BPMusic {
+    pub track: &'static str,
+    pub fade: f32,
+    pub fading_out: bool,
+}
+
+/// Smoothed ducking factor applied on top of the music bus.
+#[derive(Resource)]
+pub struct BPMusicDuck(pub f32);
+
+impl Default for BPMusicDuck {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+fn select_music_track(mut commands: Commands,
+                      asset_server: Res<AssetServer>,
+                      game_state: Res<State<BPGameState>>,
+                      current_level: Res<BPCurrentLevel>,
+                      mut music_query: Query<&mut BPMusic>) {
+    let track = music_track(*game_state.get(), *current_level);
+
+    let mut already_playing = false;
+    for mut music in &mut music_query {
+        if music.track == track && !music.fading_out {
+            already_playing = true;
+        } else {
+            music.fading_out = true;
+        }
+    }
+
+    if already_playing {
+        return;
+    }
+
+    commands.spawn((
+        BPMusic { track, fade: 0., fading_out: false },
+        AudioPlayer::new(asset_server.load(track)),
+        PlaybackSettings::LOOP.with_volume(Volume::Linear(0.)),
+    ));
+}
+
+fn update_music_duck(menu_state: Res<State<BPMenuState>>,
+                     mut duck: ResMut<BPMusicDuck>,
+                     time: Res<Time<Real>>) {
+    let target = match menu_state.get() {
+        BPMenuState::Closed => 1.0,
+        _ => DUCKED_VOLUME,
+    };
+
+    duck.0 = duck.0.lerp(target, (5.0 * time.delta_secs()).min(1.0));
+}
+
+// Real time is used so fades keep going while the game is paused
+fn fade_music(mut commands: Commands,
+              mut music_query: Query<(Entity, &mut BPMusic, Option<&mut AudioSink>)>,
+              settings: Res<BPSettings>,
+              duck: Res<BPMusicDuck>,
+              time: Res<Time<Real>>) {
+    let step = time.delta_secs() / CROSSFADE_SECS;
+
+    for (entity, mut music, sink) in &mut music_query {
+        if music.fading_out {
+            music.fade = (music.fade - step).max(0.);
+            if music.fade <= 0. {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        } else {
+            music.fade = (music.fade + step).min(1.);
+        }
+
+        // The sink only exists once the track has been loaded
+        if let Some(mut sink) = sink {
+            let volume = music.fade * duck.0 * BPAudioBus::Music.volume(&settings);
+            sink.set_volume(Volume::Linear(volume));
+        }
+    }
+}
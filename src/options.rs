@@ -0,0 +1,288 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::controls::{BPControls, BPRebindState};
+use crate::player::MAX_PLAYERS;
+use crate::settings::BPSettings;
+use crate::state::BPMenuState;
+
+pub struct BPOptionsPlugin;
+
+impl Plugin for BPOptionsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_plugins(InputManagerPlugin::<BPMenuAction>::default())
+            .init_resource::<BPOptionsSelection>()
+            .add_systems(Startup, setup_menu_input)
+            .add_systems(Update, toggle_options_menu)
+            .add_systems(OnExit(BPMenuState::Closed), pause_game)
+            .add_systems(OnEnter(BPMenuState::Closed), resume_game)
+            .add_systems(OnEnter(BPMenuState::Options), setup_options_ui)
+            .add_systems(OnExit(BPMenuState::Options), (cleanup_options_ui, save_settings))
+            .add_systems(Update, (options_navigation, update_options_ui)
+                .chain()
+                .run_if(in_state(BPMenuState::Options)));
+    }
+}
+
+#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect, Serialize, Deserialize)]
+pub enum BPMenuAction {
+    ToggleOptions,
+    Confirm,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Component)]
+pub struct BPMenuInputMarker;
+
+#[derive(Component)]
+pub struct BPUiOptionsMarker;
+
+/// One line of the options screen, pointing at the entry it displays.
+#[derive(Component)]
+pub struct BPOptionsRow(pub BPOptionsEntry);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BPOptionsEntry {
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    WindowMode,
+    Vsync,
+    ScreenShake,
+    ParticleDensity,
+    Players,
+    PlayerCollision,
+    /// Controller of the player with this `BPPlayerIndex`.
+    PlayerController(usize),
+    Controls,
+}
+
+impl BPOptionsEntry {
+    pub fn all() -> Vec<BPOptionsEntry> {
+        let mut entries = vec![
+            BPOptionsEntry::MasterVolume,
+            BPOptionsEntry::MusicVolume,
+            BPOptionsEntry::SfxVolume,
+            BPOptionsEntry::WindowMode,
+            BPOptionsEntry::Vsync,
+            BPOptionsEntry::ScreenShake,
+            BPOptionsEntry::ParticleDensity,
+            BPOptionsEntry::Players,
+            BPOptionsEntry::PlayerCollision,
+        ];
+        entries.extend((0..MAX_PLAYERS).map(BPOptionsEntry::PlayerController));
+        entries.push(BPOptionsEntry::Controls);
+        entries
+    }
+
+    fn label(self, settings: &BPSettings) -> String {
+        match self {
+            BPOptionsEntry::MasterVolume => format!("Master volume: {}", percent(settings.master_volume)),
+            BPOptionsEntry::MusicVolume => format!("Music volume: {}", percent(settings.music_volume)),
+            BPOptionsEntry::SfxVolume => format!("SFX volume: {}", percent(settings.sfx_volume)),
+            BPOptionsEntry::WindowMode => format!("Window mode: {}", settings.window_mode.label()),
+            BPOptionsEntry::Vsync => format!("VSync: {}", if settings.vsync { "On" } else { "Off" }),
+            BPOptionsEntry::ScreenShake => format!("Screen shake: {}", percent(settings.screen_shake)),
+            BPOptionsEntry::ParticleDensity => format!("Particle density: {}", percent(settings.particle_density)),
+            BPOptionsEntry::Players => format!("Players: {} (next game)", settings.player_count),
+            BPOptionsEntry::PlayerCollision => format!("Players collide: {}", if settings.players_collide { "On" } else { "Off" }),
+            BPOptionsEntry::PlayerController(index) => format!("Player {} controller: {}", index + 1, settings.player_controllers[index].label()),
+            BPOptionsEntry::Controls => "Controls...".to_string(),
+        }
+    }
+
+    /// Changes the value behind this entry. `direction` is -1 for left and 1 for right.
+    fn adjust(self, settings: &mut BPSettings, direction: f32) {
+        const STEP: f32 = 0.1;
+
+        match self {
+            BPOptionsEntry::MasterVolume => settings.master_volume = step(settings.master_volume, STEP * direction),
+            BPOptionsEntry::MusicVolume => settings.music_volume = step(settings.music_volume, STEP * direction),
+            BPOptionsEntry::SfxVolume => settings.sfx_volume = step(settings.sfx_volume, STEP * direction),
+            BPOptionsEntry::WindowMode => {
+                settings.window_mode = if direction > 0. {
+                    settings.window_mode.next()
+                } else {
+                    settings.window_mode.previous()
+                };
+            }
+            BPOptionsEntry::Vsync => settings.vsync = !settings.vsync,
+            BPOptionsEntry::ScreenShake => settings.screen_shake = step(settings.screen_shake, STEP * direction),
+            BPOptionsEntry::ParticleDensity => settings.particle_density = step(settings.particle_density, STEP * direction),
+            BPOptionsEntry::Players => {
+                settings.player_count = (settings.player_count as i32 + direction as i32).clamp(1, MAX_PLAYERS as i32) as usize;
+            }
+            BPOptionsEntry::PlayerCollision => settings.players_collide = !settings.players_collide,
+            BPOptionsEntry::PlayerController(index) => {
+                settings.player_controllers[index] = settings.player_controllers[index].toggled();
+            }
+            // Opens its own screen, see options_navigation
+            BPOptionsEntry::Controls => {}
+        }
+    }
+}
+
+fn percent(value: f32) -> String {
+    format!("{}%", (value * 100.).round())
+}
+
+fn step(value: f32, delta: f32) -> f32 {
+    // Round to avoid drifting values like 0.70000005 after a few steps
+    ((value + delta).clamp(0., 1.) * 10.).round() / 10.
+}
+
+#[derive(Resource, Default)]
+pub struct BPOptionsSelection(pub usize);
+
+fn setup_menu_input(mut commands: Commands,
+                    controls: Res<BPControls>){
+    commands.spawn((
+        BPMenuInputMarker,
+        controls.menu.clone(),
+    ));
+}
+
+pub fn toggle_options_menu(query: Query<&ActionState<BPMenuAction>, With<BPMenuInputMarker>>,
+                       menu_state: Res<State<BPMenuState>>,
+                       rebind_state: Res<BPRebindState>,
+                       mut next_menu_state: ResMut<NextState<BPMenuState>>){
+    let Ok(action_state) = query.single() else {
+        return;
+    };
+
+    if !action_state.just_pressed(&BPMenuAction::ToggleOptions) {
+        return;
+    }
+
+    match menu_state.get() {
+        BPMenuState::Closed => next_menu_state.set(BPMenuState::Options),
+        BPMenuState::Options => next_menu_state.set(BPMenuState::Closed),
+        BPMenuState::Controls => {
+            // A rebind in progress uses Escape to cancel itself
+            if !rebind_state.capturing {
+                next_menu_state.set(BPMenuState::Options);
+            }
+        }
+    }
+}
+
+fn pause_game(mut time: ResMut<Time<Virtual>>,
+              mut rapier_config: Query<&mut RapierConfiguration>){
+    time.pause();
+    for mut config in &mut rapier_config {
+        config.physics_pipeline_active = false;
+    }
+}
+
+fn resume_game(mut time: ResMut<Time<Virtual>>,
+               mut rapier_config: Query<&mut RapierConfiguration>){
+    time.unpause();
+    for mut config in &mut rapier_config {
+        config.physics_pipeline_active = true;
+    }
+}
+
+fn save_settings(settings: Res<BPSettings>){
+    settings.save();
+}
+
+fn setup_options_ui(mut commands: Commands){
+    commands.spawn((
+        BPUiOptionsMarker,
+        Node {
+            // Take up the whole screen
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(10.0),
+            ..default()
+        },
+        BackgroundColor(Color::BLACK.with_alpha(0.8)),
+        ZIndex(3),
+    )).with_children(|parent| {
+        parent.spawn((
+            Text::new("OPTIONS"),
+            TextFont::from_font_size(60.0),
+            TextColor(Color::WHITE),
+        ));
+
+        for entry in BPOptionsEntry::all() {
+            parent.spawn((
+                BPOptionsRow(entry),
+                Text::new(""),
+                TextFont::from_font_size(30.0),
+                TextColor(Color::WHITE),
+            ));
+        }
+
+        parent.spawn((
+            Text::new("Up/Down to select, Left/Right to change, Escape to close"),
+            TextFont::from_font_size(20.0),
+            TextColor(Color::srgb(0.6, 0.6, 0.6)),
+        ));
+    });
+}
+
+fn cleanup_options_ui(mut commands: Commands,
+                      query: Query<Entity, With<BPUiOptionsMarker>>){
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn options_navigation(query: Query<&ActionState<BPMenuAction>, With<BPMenuInputMarker>>,
+                      mut selection: ResMut<BPOptionsSelection>,
+                      mut settings: ResMut<BPSettings>,
+                      mut next_menu_state: ResMut<NextState<BPMenuState>>){
+    let Ok(action_state) = query.single() else {
+        return;
+    };
+
+    let entries = BPOptionsEntry::all();
+    let count = entries.len();
+    if action_state.just_pressed(&BPMenuAction::Up) {
+        selection.0 = (selection.0 + count - 1) % count;
+    }
+    if action_state.just_pressed(&BPMenuAction::Down) {
+        selection.0 = (selection.0 + 1) % count;
+    }
+
+    let entry = entries[selection.0];
+    if entry == BPOptionsEntry::Controls {
+        if action_state.just_pressed(&BPMenuAction::Confirm) || action_state.just_pressed(&BPMenuAction::Right) {
+            next_menu_state.set(BPMenuState::Controls);
+        }
+        return;
+    }
+
+    if action_state.just_pressed(&BPMenuAction::Left) {
+        entry.adjust(&mut settings, -1.);
+    }
+    if action_state.just_pressed(&BPMenuAction::Right) {
+        entry.adjust(&mut settings, 1.);
+    }
+}
+
+fn update_options_ui(mut query: Query<(&BPOptionsRow, &mut Text, &mut TextColor)>,
+                     selection: Res<BPOptionsSelection>,
+                     settings: Res<BPSettings>){
+    let selected = BPOptionsEntry::all()[selection.0];
+
+    for (row, mut text, mut color) in &mut query {
+        let label = row.0.label(&settings);
+        if selected == row.0 {
+            text.0 = format!("> {label} <");
+            color.0 = Color::srgb(1.0, 0.8, 0.0);
+        } else {
+            text.0 = label;
+            color.0 = Color::WHITE;
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use bevy::prelude::*;
 use rand::Rng;
+use crate::player::{player_color, player_shape, MAX_PLAYERS};
+use crate::rng::{BPRng, BPRngStream};
+use crate::settings::BPSettings;
 
 // 1. The Resource to hold shared assets
 #[derive(Resource)]
@@ -8,12 +11,34 @@ pub struct BPDustAssets {
     pub material: Handle<ColorMaterial>,
 }
 
+/// Shared assets of the afterimages, one material per player.
+#[derive(Resource)]
+pub struct BPAfterimageAssets {
+    pub mesh: Handle<Mesh>,
+    pub materials: Vec<Handle<ColorMaterial>>,
+}
+
 #[derive(Message)]
 pub struct BPSpawnDustMessage {
     pub position: Vec2,
     pub count: usize,
 }
 
+/// A faded copy of a dashing player, left behind where it was.
+#[derive(Message)]
+pub struct BPSpawnAfterimageMessage {
+    pub position: Vec2,
+    /// `BPPlayerIndex` of the player, for its color.
+    pub player: usize,
+    pub scale: Vec2,
+}
+
+#[derive(Component)]
+pub struct BPAfterimage {
+    pub lifetime: Timer,
+    pub scale: Vec2,
+}
+
 #[derive(Component)]
 pub struct BPDustParticle {
     pub velocity: Vec2,
@@ -26,9 +51,10 @@ pub struct BPParticlePlugin;
 impl Plugin for BPParticlePlugin {
     fn build(&self, app: &mut App) {
         app.add_message::<BPSpawnDustMessage>()
+            .add_message::<BPSpawnAfterimageMessage>()
             // Load assets at startup
-            .add_systems(Startup, setup_dust_assets)
-            .add_systems(Update, (spawn_dust_listener, update_particles));
+            .add_systems(Startup, (setup_dust_assets, setup_afterimage_assets))
+            .add_systems(Update, (spawn_dust_listener, spawn_afterimage_listener, update_particles, update_afterimages));
     }
 }
 
@@ -46,15 +72,31 @@ fn setup_dust_assets(
     });
 }
 
+fn setup_afterimage_assets(mut commands: Commands,
+                           mut meshes: ResMut<Assets<Mesh>>,
+                           mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands.insert_resource(BPAfterimageAssets {
+        mesh: meshes.add(player_shape()),
+        materials: (0..MAX_PLAYERS)
+            .map(|index| materials.add(player_color(index).with_alpha(0.3)))
+            .collect(),
+    });
+}
+
 fn spawn_dust_listener(
     mut commands: Commands,
     mut events: MessageReader<BPSpawnDustMessage>,
     dust_assets: Res<BPDustAssets>, // <--- Read the cached assets
+    settings: Res<BPSettings>,
+    mut rng: ResMut<BPRng>,
 ) {
-    let mut rng = rand::rng();
+    let rng = rng.stream(BPRngStream::Dust);
 
     for event in events.read() {
-        for _ in 0..event.count {
+        // Scale the amount of particles with the user setting
+        let count = (event.count as f32 * settings.particle_density).round() as usize;
+
+        for _ in 0..count {
             let vx = rng.random_range(-50.0..50.0);
             let vy = rng.random_range(10.0..60.0);
             // Random size between 3px and 8px
@@ -98,4 +140,48 @@ fn update_particles(
             commands.entity(entity).despawn();
         }
     }
-}
\ No newline at end of file
+}
+
+fn spawn_afterimage_listener(mut commands: Commands,
+                             mut events: MessageReader<BPSpawnAfterimageMessage>,
+                             afterimage_assets: Res<BPAfterimageAssets>,
+                             settings: Res<BPSettings>) {
+    // A trail is a single line of images, the density only turns it on or off
+    if settings.particle_density <= 0. {
+        events.clear();
+        return;
+    }
+
+    for event in events.read() {
+        let Some(material) = afterimage_assets.materials.get(event.player) else {
+            continue;
+        };
+
+        commands.spawn((
+            BPAfterimage {
+                lifetime: Timer::from_seconds(0.2, TimerMode::Once),
+                scale: event.scale,
+            },
+            Mesh2d(afterimage_assets.mesh.clone()),
+            MeshMaterial2d(material.clone()),
+            // Behind the players
+            Transform::from_xyz(event.position.x, event.position.y, 1.)
+                .with_scale(event.scale.extend(1.)),
+        ));
+    }
+}
+
+fn update_afterimages(mut commands: Commands,
+                      time: Res<Time>,
+                      mut query: Query<(Entity, &mut Transform, &mut BPAfterimage)>) {
+    for (entity, mut transform, mut afterimage) in &mut query {
+        afterimage.lifetime.tick(time.delta());
+        if afterimage.lifetime.is_finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        // Shrinks away instead of fading, so every image can share its player's material
+        transform.scale = (afterimage.scale * afterimage.lifetime.fraction_remaining()).extend(1.);
+    }
+}
@@ -1,6 +1,29 @@
-use bevy_rapier2d::geometry::Group;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
 
 pub const PLAYER_GROUP: Group = Group::GROUP_1;
 pub const PAWN_GROUP: Group  = Group::GROUP_2;
 pub const WORLD_GROUP: Group  = Group::GROUP_3;
-pub const INVISIBLE_WALL_GROUP: Group = Group::GROUP_4;
\ No newline at end of file
+pub const INVISIBLE_WALL_GROUP: Group = Group::GROUP_4;
+/// Solid from above only, see `BPPlatformPlugin`.
+pub const ONE_WAY_PLATFORM_GROUP: Group = Group::GROUP_5;
+
+pub const PIXELS_PER_METER: f32 = 100.0;
+
+pub struct BPPhysicsPlugin;
+
+impl Plugin for BPPhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(fixed_timestep_mode())
+            .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(PIXELS_PER_METER).in_fixed_schedule());
+    }
+}
+
+/// Physics steps once per fixed tick, with the gameplay systems, so a run can be replayed tick by tick.
+pub fn fixed_timestep_mode() -> TimestepMode {
+    TimestepMode::Fixed {
+        dt: Time::<Fixed>::default().timestep().as_secs_f32(),
+        substeps: 1,
+    }
+}
@@ -0,0 +1,296 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::controller::BPControllerSystems;
+use crate::physic::ONE_WAY_PLATFORM_GROUP;
+use crate::player::{player_shape, BPPlayerMarker};
+use crate::state::BPGameState;
+
+/// How long a drop lets a player fall through one-way platforms, enough to sink into the one it stood on.
+const DROP_THROUGH_DURATION: f32 = 0.25;
+/// Upward speed above which a player passes through one-way platforms.
+const RISING_SPEED: f32 = 10.;
+/// How far above or below the top of a platform something can be and still be standing on it.
+const STANDING_TOLERANCE: f32 = 5.;
+
+// One-way platforms are solid from above only: players jump up through them, land on them,
+// and drop through them with down and jump. Each player takes `ONE_WAY_PLATFORM_GROUP` out of its own
+// collision filters while it should pass through, so both controllers and every player behave on their own.
+//
+// Platforms given a path in the level data move along it as kinematic bodies, and bring
+// every `BPPassenger` standing on them along.
+pub struct BPPlatformPlugin;
+
+impl Plugin for BPPlatformPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<BPPlatformPath>()
+            // After the gameplay systems and the character controller decided where players go
+            .add_systems(FixedUpdate, (move_platforms, update_one_way_collisions)
+                .chain()
+                .after(BPControllerSystems::Move)
+                .before(PhysicsSet::SyncBackend)
+                .run_if(in_state(BPGameState::InGame)));
+    }
+}
+
+/// True if something whose feet are `feet` below `position` stands on the box at `center`.
+pub fn stands_on(center: Vec2, half_size: Vec2, position: Vec2, feet: f32) -> bool {
+    (position.x - center.x).abs() < half_size.x + feet
+        && (position.y - feet - (center.y + half_size.y)).abs() <= STANDING_TOLERANCE
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BPOneWayPlatform {
+    pub half_size: Vec2,
+}
+
+impl BPOneWayPlatform {
+    fn top(&self, transform: &Transform) -> f32 {
+        transform.translation.y + self.half_size.y
+    }
+
+    /// True if a player of this radius at `position` stands on the platform.
+    pub fn supports(&self, transform: &Transform, position: Vec2, radius: f32) -> bool {
+        stands_on(transform.translation.xy(), self.half_size, position, radius)
+    }
+
+    /// True if a player of this radius at `position` is partly inside the platform, below its top.
+    fn overlaps(&self, transform: &Transform, position: Vec2, radius: f32) -> bool {
+        let bottom = transform.translation.y - self.half_size.y;
+        (position.x - transform.translation.x).abs() < self.half_size.x + radius
+            && position.y - radius < self.top(transform) - STANDING_TOLERANCE
+            && position.y + radius > bottom
+    }
+}
+
+/// How a platform goes through its waypoints.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum BPPathMode {
+    /// Once, then stays on the last waypoint.
+    #[default]
+    Linear,
+    /// Back and forth between the first and the last waypoint.
+    PingPong,
+    /// From the last waypoint straight back to the first one, forever.
+    Loop,
+}
+
+/// How a platform speeds up and slows down between two waypoints.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum BPEasing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl BPEasing {
+    /// Maps the share of a segment covered, from 0 to 1, to the share of the distance travelled.
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            BPEasing::Linear => t,
+            BPEasing::EaseIn => t * t,
+            BPEasing::EaseOut => 1. - (1. - t) * (1. - t),
+            BPEasing::EaseInOut => t * t * (3. - 2. * t),
+        }
+    }
+}
+
+/// Where a moving platform goes, as stored in the level data.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Reflect)]
+#[serde(default)]
+pub struct BPPlatformPath {
+    /// Relative to the position of the platform, which is the first stop.
+    pub waypoints: Vec<Vec2>,
+    pub mode: BPPathMode,
+    pub easing: BPEasing,
+    /// Average speed between two waypoints, in world units per second.
+    pub speed: f32,
+    /// Time spent on each waypoint, in seconds.
+    pub pause: f32,
+}
+
+impl Default for BPPlatformPath {
+    fn default() -> Self {
+        Self {
+            waypoints: Vec::new(),
+            mode: BPPathMode::PingPong,
+            easing: BPEasing::EaseInOut,
+            speed: 150.,
+            pause: 0.5,
+        }
+    }
+}
+
+/// Something carried by the moving platforms it stands on. Holds the distance from its center to its feet.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BPPassenger(pub f32);
+
+#[derive(Component, Debug, Clone)]
+pub struct BPMovingPlatform {
+    pub half_size: Vec2,
+    /// Every stop in world units, starting with the spawn position.
+    points: Vec<Vec2>,
+    mode: BPPathMode,
+    easing: BPEasing,
+    speed: f32,
+    pause: f32,
+    /// Indices in `points` of the segment being travelled. Equal when the platform stopped for good.
+    from: usize,
+    to: usize,
+    /// Distance covered from `from`, without easing.
+    progress: f32,
+    pause_left: f32,
+    /// Direction of a ping-pong.
+    forward: bool,
+}
+
+impl BPMovingPlatform {
+    pub fn new(position: Vec2, half_size: Vec2, path: &BPPlatformPath) -> Self {
+        let mut points = vec![position];
+        points.extend(path.waypoints.iter().map(|waypoint| position + *waypoint));
+
+        Self {
+            half_size,
+            to: if points.len() > 1 { 1 } else { 0 },
+            points,
+            mode: path.mode,
+            easing: path.easing,
+            speed: path.speed,
+            pause: path.pause,
+            from: 0,
+            progress: 0.,
+            pause_left: path.pause,
+            forward: true,
+        }
+    }
+
+    /// Moves along the path for `delta` seconds and returns the new position.
+    fn advance(&mut self, delta: f32) -> Vec2 {
+        if self.pause_left > 0. {
+            self.pause_left -= delta;
+        } else if self.from != self.to {
+            self.progress += self.speed * delta;
+            if self.progress >= self.points[self.from].distance(self.points[self.to]) {
+                self.arrive();
+            }
+        }
+
+        self.position()
+    }
+
+    fn position(&self) -> Vec2 {
+        let (from, to) = (self.points[self.from], self.points[self.to]);
+        let length = from.distance(to);
+        if length <= 0. {
+            return from;
+        }
+
+        from.lerp(to, self.easing.apply((self.progress / length).min(1.)))
+    }
+
+    fn arrive(&mut self) {
+        let last = self.points.len() - 1;
+        self.from = self.to;
+        self.progress = 0.;
+        self.pause_left = self.pause;
+
+        self.to = match self.mode {
+            BPPathMode::Linear => (self.from + 1).min(last),
+            BPPathMode::Loop => (self.from + 1) % self.points.len(),
+            BPPathMode::PingPong => {
+                if self.from == last {
+                    self.forward = false;
+                } else if self.from == 0 {
+                    self.forward = true;
+                }
+
+                if self.forward { self.from + 1 } else { self.from - 1 }
+            }
+        };
+    }
+}
+
+/// Time left falling through one-way platforms after a drop.
+#[derive(Component, Default, Debug)]
+pub struct BPPlatformDrop(pub f32);
+
+impl BPPlatformDrop {
+    pub fn start(&mut self) {
+        self.0 = DROP_THROUGH_DURATION;
+    }
+}
+
+fn move_platforms(mut platform_query: Query<(&mut Transform, &mut BPMovingPlatform)>,
+                  mut passenger_query: Query<(&mut Transform,
+                                              &BPPassenger,
+                                              Option<&mut KinematicCharacterController>), Without<BPMovingPlatform>>,
+                  time: Res<Time>) {
+    for (mut platform_transform, mut platform) in &mut platform_query {
+        let position = platform_transform.translation.xy();
+        let movement = platform.advance(time.delta_secs()) - position;
+        if movement == Vec2::ZERO {
+            continue;
+        }
+
+        for (mut transform, passenger, character_controller) in &mut passenger_query {
+            if !stands_on(position, platform.half_size, transform.translation.xy(), passenger.0) {
+                continue;
+            }
+
+            match character_controller {
+                // Character controllers move through their translation, so they still stop at walls
+                Some(mut character_controller) => {
+                    let translation = character_controller.translation.unwrap_or_default();
+                    character_controller.translation = Some(translation + movement);
+                }
+                None => transform.translation += movement.extend(0.),
+            }
+        }
+
+        platform_transform.translation += movement.extend(0.);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_one_way_collisions(mut player_query: Query<(&Transform,
+                                                      &Velocity,
+                                                      &ExternalImpulse,
+                                                      &mut BPPlatformDrop,
+                                                      &mut CollisionGroups,
+                                                      Option<&mut KinematicCharacterController>), With<BPPlayerMarker>>,
+                             platform_query: Query<(&Transform, &BPOneWayPlatform)>,
+                             time: Res<Time>) {
+    let radius = player_shape().radius;
+
+    for (transform, velocity, external_impulse, mut drop, mut collision_groups, character_controller) in &mut player_query {
+        drop.0 = (drop.0 - time.delta_secs()).max(0.);
+
+        let position = transform.translation.xy();
+        // Once inside a platform the player keeps going, whichever way, until it is out.
+        // A dynamic jump only shows in the velocity after the step, its impulse counts as rising.
+        let passing = drop.0 > 0.
+            || velocity.linvel.y > RISING_SPEED
+            || external_impulse.impulse.y > 0.
+            || platform_query.iter().any(|(platform_transform, platform)| platform.overlaps(platform_transform, position, radius));
+
+        let groups = with_one_way_filter(*collision_groups, !passing);
+        if *collision_groups != groups {
+            *collision_groups = groups;
+        }
+
+        if let Some(mut character_controller) = character_controller {
+            let filter_groups = character_controller.filter_groups.map(|groups| with_one_way_filter(groups, !passing));
+            if character_controller.filter_groups != filter_groups {
+                character_controller.filter_groups = filter_groups;
+            }
+        }
+    }
+}
+
+fn with_one_way_filter(mut groups: CollisionGroups, solid: bool) -> CollisionGroups {
+    groups.filters.set(ONE_WAY_PLATFORM_GROUP, solid);
+    groups
+}
@@ -2,11 +2,22 @@ use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use leafwing_input_manager::prelude::*;
 use rand::Rng;
-use crate::BPLevelElement;
+use serde::{Deserialize, Serialize};
+use crate::abilities::BPAbilities;
+use crate::camera::BPCameraShake;
+use crate::controller::{character_filter_groups, set_player_controller, BPControllerSystems, BPPlayerController};
+use crate::controls::BPControls;
 use crate::enemy::BPEnemyMarker;
-use crate::particle::BPSpawnDustMessage;
-use crate::physic::{PAWN_GROUP, PLAYER_GROUP, WORLD_GROUP};
+use crate::input::{BPInputLatch, BPPlayerInput};
+use crate::level::BPLevelElement;
+use crate::particle::{BPSpawnAfterimageMessage, BPSpawnDustMessage};
+use crate::physic::{ONE_WAY_PLATFORM_GROUP, PAWN_GROUP, PLAYER_GROUP, WORLD_GROUP};
+use crate::platform::{BPOneWayPlatform, BPPassenger, BPPlatformDrop};
+use crate::replay::BPReplayPlayback;
+use crate::rng::{BPRng, BPRngStream};
+use crate::settings::BPSettings;
 use crate::state::BPGameState;
+use crate::tuning::BPPlayerTuning;
 use crate::utils::BPGameCleanupMarker;
 
 pub struct BPPlayerPlugin;
@@ -15,43 +26,249 @@ impl Plugin for BPPlayerPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_plugins(InputManagerPlugin::<Action>::default())
-            .add_systems(FixedUpdate, (move_player, jump_player, procedural_animation_system)
+            .add_message::<BPGameplayMessage>()
+            // Dust is only visual, but gameplay asks for it whether or not particles are drawn
+            .add_message::<BPSpawnDustMessage>()
+            .add_message::<BPSpawnAfterimageMessage>()
+            .add_systems(FixedUpdate, (detect_player_contacts, move_player, jump_player, dash_player, ground_pound_player, procedural_animation_system)
+                .chain()
+                .before(BPControllerSystems::Move)
+                .before(PhysicsSet::SyncBackend)
                 .run_if(in_state(BPGameState::InGame)))
-            .add_systems(Update, (handle_player_collision)
-                .run_if(in_state(BPGameState::InGame)));
+            .add_systems(FixedUpdate, handle_player_collision
+                .after(BPControllerSystems::Read)
+                .after(PhysicsSet::Writeback)
+                .run_if(in_state(BPGameState::InGame)))
+            // A replay keeps the collision rule and controllers it was recorded with
+            .add_systems(Update, (
+                apply_player_collision_rule,
+                apply_player_controllers,
+            ).run_if(resource_changed::<BPSettings>.and(not(resource_exists::<BPReplayPlayback>))));
     }
 }
 
-#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect)]
+#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect, Serialize, Deserialize)]
 pub enum Action {
     #[actionlike(Axis)]
     Run,
     Jump,
+    /// Held with a jump to drop through a one-way platform. In the air, starts a ground pound once unlocked.
+    Down,
+    Dash,
+}
+
+/// Things that happened to the players, for audio and other feedback to react to.
+#[derive(Message, Clone, Copy, Debug)]
+pub enum BPGameplayMessage {
+    Jumped,
+    Landed,
+    Stomped,
+    Died,
+    Victory,
 }
 
+/// How many players can join a local co-op game.
+pub const MAX_PLAYERS: usize = 2;
+
 #[derive(Component)]
-#[require(BPGameCleanupMarker)]
+#[require(BPGameCleanupMarker, BPInputLatch, BPPlayerInput)]
 pub struct BPPlayerMarker;
 
-#[derive(Component)]
-pub struct BPPlayerRunStrength(pub f32);
+/// Which player this is, starting at 0. Used to pick the input map, gamepad and color.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BPPlayerIndex(pub usize);
 
+/// Impulse of a jump with the dynamic controller.
 #[derive(Component)]
 pub struct BPPlayerJumpStrength(pub f32);
 
+/// Upward speed of a jump with the kinematic controller.
+#[derive(Component)]
+pub struct BPPlayerJumpSpeed(pub f32);
+
+/// Whether the double jump was used since the player last stood on something.
+#[derive(Component, Default, Debug)]
+pub struct BPPlayerAirJump(pub bool);
+
+/// A straight fall from the air, started with down.
+#[derive(Component, Debug)]
+pub struct BPPlayerGroundPound {
+    /// From `BPPlayerTuning`.
+    pub speed: f32,
+    pub active: bool,
+}
+
+/// How far from the edge of a player the ground or a wall still counts as touching it.
+const CONTACT_DISTANCE: f32 = 3.;
+
+/// What a player touches, found at the start of each tick before it moves.
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq)]
+pub struct BPPlayerContacts {
+    pub grounded: bool,
+    /// Side of the wall being touched, -1 for the left and 1 for the right.
+    pub wall: Option<f32>,
+}
+
+impl BPPlayerContacts {
+    /// Side of the wall the player slides down, if it is off the ground.
+    pub fn sliding_wall(&self) -> Option<f32> {
+        self.wall.filter(|_| !self.grounded)
+    }
+}
+
+/// Wall slide and wall jump, from `BPPlayerTuning`.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct BPPlayerWallControl {
+    pub slide_speed: f32,
+    pub jump_impulse: Vec2,
+    pub lockout: f32,
+}
+
+impl BPPlayerWallControl {
+    pub fn from_tuning(tuning: &BPPlayerTuning) -> Self {
+        Self {
+            slide_speed: tuning.wall_slide_speed,
+            jump_impulse: tuning.wall_jump_impulse,
+            lockout: tuning.wall_jump_lockout,
+        }
+    }
+}
+
+/// Time left ignoring the run input, so a wall jump is not steered straight back into the wall.
+#[derive(Component, Default, Debug)]
+pub struct BPPlayerRunLockout(pub f32);
+
+/// Dash values, from `BPPlayerTuning`.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct BPPlayerDashControl {
+    pub speed: f32,
+    pub duration: f32,
+    pub cooldown: f32,
+    pub air_dashes: u32,
+    pub through_enemies: bool,
+    /// Given back when a dash ends.
+    pub gravity_scale: f32,
+}
+
+impl BPPlayerDashControl {
+    pub fn from_tuning(tuning: &BPPlayerTuning) -> Self {
+        Self {
+            speed: tuning.dash_speed,
+            duration: tuning.dash_duration,
+            cooldown: tuning.dash_cooldown,
+            air_dashes: tuning.air_dashes,
+            through_enemies: tuning.dash_through_enemies,
+            gravity_scale: tuning.gravity_scale,
+        }
+    }
+}
+
+/// Where a player stands with its dashes.
+#[derive(Component, Debug)]
+pub struct BPPlayerDash {
+    /// Time left in the current dash, 0 when not dashing.
+    pub time_left: f32,
+    /// Time left before the next dash, counted from the start of the current one.
+    pub cooldown_left: f32,
+    pub air_dashes_used: u32,
+    /// -1 for the left and 1 for the right. Dashes go where the player last ran.
+    pub direction: f32,
+}
+
+impl Default for BPPlayerDash {
+    fn default() -> Self {
+        Self {
+            time_left: 0.,
+            cooldown_left: 0.,
+            air_dashes_used: 0,
+            direction: 1.,
+        }
+    }
+}
+
+impl BPPlayerDash {
+    pub fn is_dashing(&self) -> bool {
+        self.time_left > 0.
+    }
+
+    /// True if touching an enemy is harmless right now.
+    pub fn passes_through_enemies(&self, control: &BPPlayerDashControl) -> bool {
+        self.is_dashing() && control.through_enemies
+    }
+}
+
+/// Upward speed of the bounce off a stomped enemy with the kinematic controller.
+/// Dynamic players get an impulse that ends up at about the same speed.
+const STOMP_BOUNCE_SPEED: f32 = 500.;
+
+/// How running changes the horizontal speed, from `BPPlayerTuning`. Speeds are in world units per second.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct BPPlayerRunControl {
+    pub max_speed: f32,
+    pub acceleration: f32,
+    pub deceleration: f32,
+    pub air_control: f32,
+    pub turn_around_boost: f32,
+}
+
+impl BPPlayerRunControl {
+    pub fn from_tuning(tuning: &BPPlayerTuning) -> Self {
+        Self {
+            max_speed: tuning.max_speed,
+            acceleration: tuning.acceleration,
+            deceleration: tuning.deceleration,
+            air_control: tuning.air_control,
+            turn_around_boost: tuning.turn_around_boost,
+        }
+    }
+
+    /// Horizontal speed after running with `run` for `delta` seconds, starting at `speed`.
+    pub fn next_speed(&self, run: f32, speed: f32, grounded: bool, delta: f32) -> f32 {
+        let target = run.clamp(-1., 1.) * self.max_speed;
+
+        let mut rate = if run == 0. || (speed.abs() > target.abs() && speed * target >= 0.) {
+            // Letting go, or going faster than asked
+            self.deceleration
+        } else if speed * target < 0. {
+            // Pushing against the current direction
+            self.acceleration * self.turn_around_boost
+        } else {
+            self.acceleration
+        };
+
+        if !grounded {
+            rate *= self.air_control;
+        }
+
+        let step = rate * delta;
+        speed + (target - speed).clamp(-step, step)
+    }
+}
+
 #[derive(Bundle)]
 pub struct BPPlayerBundle {
     // Markers & Logic
     pub marker: BPPlayerMarker,
+    pub index: BPPlayerIndex,
 
-    pub run_strength: BPPlayerRunStrength,
     pub jump_strength: BPPlayerJumpStrength,
+    pub jump_speed: BPPlayerJumpSpeed,
+    pub platform_drop: BPPlatformDrop,
+    pub passenger: BPPassenger,
+    pub run_control: BPPlayerRunControl,
+    pub wall_control: BPPlayerWallControl,
+    pub run_lockout: BPPlayerRunLockout,
+    pub contacts: BPPlayerContacts,
+    pub dash_control: BPPlayerDashControl,
+    pub dash: BPPlayerDash,
+    pub abilities: BPAbilities,
+    pub air_jump: BPPlayerAirJump,
+    pub ground_pound: BPPlayerGroundPound,
 
     // Physics
     pub rigid_body: RigidBody,
     pub collider: Collider,
     pub locked_axes: LockedAxes,
-    pub external_force: ExternalForce,
     pub external_impulse: ExternalImpulse,
     pub damping: Damping,
     pub restitution: Restitution,
@@ -63,87 +280,263 @@ pub struct BPPlayerBundle {
     // Input (The Bundle from Leafwing)
     pub input_map: InputMap<Action>,
 
-    // Visuals
-    pub mesh: Mesh2d,
-    pub material: MeshMaterial2d<ColorMaterial>,
     pub transform: Transform,
 }
 
 impl BPPlayerBundle {
-    pub fn new(meshes: &mut ResMut<Assets<Mesh>>,
-               materials: &mut ResMut<Assets<ColorMaterial>>) -> Self {
-
-        let input_map = InputMap::default()
-            .with_axis(Action::Run, VirtualAxis::new(KeyCode::KeyA, KeyCode::KeyD))
-            .with(Action::Jump, KeyCode::Space);
+    pub fn new(index: usize,
+               controls: &BPControls,
+               tuning: &BPPlayerTuning,
+               abilities: BPAbilities,
+               players_collide: bool) -> Self {
 
-        let mut player_damping = Damping::default();
-        player_damping.linear_damping = 5.;
+        let player_damping = Damping { linear_damping: tuning.linear_damping, ..default() };
 
         Self {
             marker: BPPlayerMarker {},
-            jump_strength: BPPlayerJumpStrength(2500000.),
-            run_strength: BPPlayerRunStrength(9500000.),
-            mesh: Mesh2d(meshes.add(Circle::new(25.))),
-            material: MeshMaterial2d(materials.add(Color::srgb(5.25, 8.4, 8.1))), // RGB values exceed 1 to achieve a bright color for the bloom effect
-            transform: Transform::from_xyz(0., 0., 2.),
+            index: BPPlayerIndex(index),
+            jump_strength: BPPlayerJumpStrength(tuning.jump_strength),
+            jump_speed: BPPlayerJumpSpeed(tuning.jump_speed),
+            platform_drop: BPPlatformDrop::default(),
+            passenger: BPPassenger(player_shape().radius),
+            run_control: BPPlayerRunControl::from_tuning(tuning),
+            wall_control: BPPlayerWallControl::from_tuning(tuning),
+            run_lockout: BPPlayerRunLockout::default(),
+            contacts: BPPlayerContacts::default(),
+            dash_control: BPPlayerDashControl::from_tuning(tuning),
+            dash: BPPlayerDash::default(),
+            abilities,
+            air_jump: BPPlayerAirJump::default(),
+            ground_pound: BPPlayerGroundPound { speed: tuning.ground_pound_speed, active: false },
+            transform: Transform::from_xyz(index as f32 * 80., 0., 2.),
             rigid_body: RigidBody::Dynamic,
             locked_axes: LockedAxes::ROTATION_LOCKED,
-            collider: Collider::ball(25.),
-            restitution: Restitution::coefficient(0.1),
+            collider: Collider::ball(player_shape().radius),
+            restitution: Restitution::coefficient(tuning.restitution),
             physic_events: ActiveEvents::COLLISION_EVENTS,
-            external_force: ExternalForce::default(),
-            gravity_scale: GravityScale(3.0),
+            gravity_scale: GravityScale(tuning.gravity_scale),
             external_impulse: ExternalImpulse::default(),
             velocity: Velocity::default(),
             damping: player_damping,
-            input_map,
-            collision_groups: CollisionGroups::new(
-                PLAYER_GROUP,
-                PLAYER_GROUP | WORLD_GROUP | PAWN_GROUP,
-            )
+            input_map: controls.players[index].clone(),
+            collision_groups: player_collision_groups(players_collide),
+        }
+    }
+}
+
+pub fn player_shape() -> Circle {
+    Circle::new(25.)
+}
+
+pub fn player_color(index: usize) -> Color {
+    // RGB values exceed 1 to achieve a bright color for the bloom effect
+    match index {
+        0 => Color::srgb(5.25, 8.4, 8.1),
+        _ => Color::srgb(8.4, 6.3, 1.5),
+    }
+}
+
+/// Players always collide with the world and pawns. Whether they bump into each other is a co-op rule.
+pub fn player_collision_groups(players_collide: bool) -> CollisionGroups {
+    let mut filters = WORLD_GROUP | PAWN_GROUP | ONE_WAY_PLATFORM_GROUP;
+    if players_collide {
+        filters |= PLAYER_GROUP;
+    }
+
+    CollisionGroups::new(PLAYER_GROUP, filters)
+}
+
+fn apply_player_collision_rule(settings: Res<BPSettings>,
+                               mut query: Query<(&mut CollisionGroups, Option<&mut KinematicCharacterController>), With<BPPlayerMarker>>) {
+    let groups = player_collision_groups(settings.players_collide);
+    let character_groups = Some(character_filter_groups(settings.players_collide));
+    for (mut collision_groups, character_controller) in &mut query {
+        if *collision_groups != groups {
+            *collision_groups = groups;
+        }
+
+        if let Some(mut character_controller) = character_controller
+            && character_controller.filter_groups != character_groups {
+            character_controller.filter_groups = character_groups;
         }
     }
 }
 
-fn move_player(mut query: Query<(&ActionState<Action>,
-                                 &BPPlayerRunStrength,
-                                 &Velocity,
+fn apply_player_controllers(mut commands: Commands,
+                            settings: Res<BPSettings>,
+                            tuning: Res<BPPlayerTuning>,
+                            query: Query<(Entity, &BPPlayerIndex, &BPPlayerController), With<BPPlayerMarker>>) {
+    for (entity, index, controller) in &query {
+        let wanted = settings.player_controllers[index.0];
+        if *controller != wanted {
+            set_player_controller(&mut commands.entity(entity), wanted, &tuning, settings.players_collide);
+        }
+    }
+}
+
+fn detect_player_contacts(mut query: Query<(&Transform, &CollisionGroups, &mut BPPlayerContacts), With<BPPlayerMarker>>,
+                          rapier_context: ReadRapierContext) {
+    let Ok(rapier_context) = rapier_context.single() else {
+        return;
+    };
+    let radius = player_shape().radius;
+
+    for (transform, collision_groups, mut contacts) in &mut query {
+        let position = transform.translation.xy();
+        // Only what the player can currently collide with, so one-way platforms being passed through don't count
+        let touches = |direction: Vec2, groups: Group, reach: f32| {
+            let filter = QueryFilter::default()
+                .exclude_sensors()
+                .groups(CollisionGroups::new(PLAYER_GROUP, groups & collision_groups.filters));
+            rapier_context.cast_ray(position, direction, reach + CONTACT_DISTANCE, true, filter).is_some()
+        };
+
+        // The collider follows the squash and stretch of the animation
+        let grounded = touches(Vec2::NEG_Y, WORLD_GROUP | ONE_WAY_PLATFORM_GROUP, radius * transform.scale.y);
+        let wall = [-1., 1.]
+            .into_iter()
+            .find(|side| touches(Vec2::new(*side, 0.), WORLD_GROUP, radius * transform.scale.x));
+
+        let new_contacts = BPPlayerContacts { grounded, wall };
+        if *contacts != new_contacts {
+            *contacts = new_contacts;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn move_player(mut query: Query<(&BPPlayerInput,
+                                 &BPPlayerRunControl,
+                                 &BPPlayerWallControl,
+                                 &BPPlayerContacts,
+                                 &BPAbilities,
+                                 &BPPlayerController,
+                                 &Damping,
                                  &Transform,
-                                 &mut ExternalForce), With<BPPlayerMarker>>,
-                mut particle_writer: MessageWriter<BPSpawnDustMessage>) {
-    for (action_state,
-        run_strength,
-        velocity,
+                                 &mut BPPlayerRunLockout,
+                                 &mut Velocity), With<BPPlayerMarker>>,
+                mut particle_writer: MessageWriter<BPSpawnDustMessage>,
+                mut rng: ResMut<BPRng>,
+                time: Res<Time>) {
+    let rng = rng.stream(BPRngStream::Movement);
+    let delta = time.delta_secs();
+
+    for (input,
+        run_control,
+        wall_control,
+        contacts,
+        abilities,
+        controller,
+        damping,
         transform,
-        mut external_force) in &mut query {
-        if let Some(axis_data) = action_state.axis_data(&Action::Run) {
-            external_force.force = Vec2::new(axis_data.value * run_strength.0,0.);
-
-            if velocity.linvel.x.abs() > 50.0 {
-                // 10% chance per frame to spawn a dust mote
-                if rand::rng().random_bool(0.1) {
-                    particle_writer.write(BPSpawnDustMessage {
-                        position: Vec2::new(transform.translation.x, transform.translation.y - 25.0),
-                        count: 1,
-                    });
-                }
+        mut run_lockout,
+        mut velocity) in &mut query {
+        let speed = if run_lockout.0 > 0. {
+            run_lockout.0 -= delta;
+            velocity.linvel.x
+        } else {
+            run_control.next_speed(input.run, velocity.linvel.x, contacts.grounded, delta)
+        };
+        velocity.linvel.x = match controller {
+            // Rapier damps the whole velocity during the step. Damping is there for the fall,
+            // the horizontal speed is already decided here so the step must land exactly on it.
+            BPPlayerController::Dynamic => speed * (1. + delta * damping.linear_damping),
+            BPPlayerController::Kinematic => speed,
+        };
+
+        if abilities.wall_jump && contacts.sliding_wall().is_some() {
+            velocity.linvel.y = velocity.linvel.y.max(-wall_control.slide_speed);
+        }
+
+        if velocity.linvel.x.abs() > 50.0 {
+            // 10% chance per frame to spawn a dust mote
+            if rng.random_bool(0.1) {
+                particle_writer.write(BPSpawnDustMessage {
+                    position: Vec2::new(transform.translation.x, transform.translation.y - 25.0),
+                    count: 1,
+                });
             }
         }
     }
 }
 
-fn jump_player(mut query: Query<(&ActionState<Action>,
+#[allow(clippy::type_complexity)]
+fn jump_player(mut query: Query<(&BPPlayerInput,
                                  &BPPlayerJumpStrength,
+                                 &BPPlayerJumpSpeed,
                                  &Transform,
-                                 &mut ExternalImpulse), With<BPPlayerMarker>>,
-               mut particle_writer: MessageWriter<BPSpawnDustMessage>) {
-    for(action_state, jump_strength, transform, mut external_impulse) in &mut query {
-        if !action_state.just_pressed(&Action::Jump) {
-            return;
+                                 &mut ExternalImpulse,
+                                 &mut Velocity,
+                                 &mut BPPlatformDrop,
+                                 &BPPlayerContacts,
+                                 &BPPlayerWallControl,
+                                 &mut BPPlayerRunLockout,
+                                 &BPAbilities,
+                                 &mut BPPlayerAirJump,
+                                 Option<&KinematicCharacterControllerOutput>), With<BPPlayerMarker>>,
+               platform_query: Query<(&Transform, &BPOneWayPlatform)>,
+               mut particle_writer: MessageWriter<BPSpawnDustMessage>,
+               mut gameplay_writer: MessageWriter<BPGameplayMessage>) {
+    for(input,
+        jump_strength,
+        jump_speed,
+        transform,
+        mut external_impulse,
+        mut velocity,
+        mut platform_drop,
+        contacts,
+        wall_control,
+        mut run_lockout,
+        abilities,
+        mut air_jump,
+        character_output) in &mut query {
+        // The character controller knows best when it stands on something
+        let grounded = character_output.map_or(contacts.grounded, |output| output.grounded);
+        if grounded && air_jump.0 {
+            air_jump.0 = false;
         }
 
-        external_impulse.impulse = Vec2::new(0., jump_strength.0);
+        if !input.jump_pressed {
+            continue;
+        }
+
+        let position = transform.translation.xy();
+        if input.down_held
+            && platform_query.iter().any(|(platform_transform, platform)| platform.supports(platform_transform, position, player_shape().radius)) {
+            platform_drop.start();
+            continue;
+        }
+
+        if abilities.wall_jump
+            && let Some(side) = contacts.sliding_wall() {
+            // Same velocity with both controllers, whatever the slide left
+            velocity.linvel = Vec2::new(-side * wall_control.jump_impulse.x, wall_control.jump_impulse.y);
+            external_impulse.impulse = Vec2::ZERO;
+            run_lockout.0 = wall_control.lockout;
+            gameplay_writer.write(BPGameplayMessage::Jumped);
+
+            particle_writer.write(BPSpawnDustMessage {
+                position: position + Vec2::new(side * 25.0, 0.0), // Side against the wall
+                count: 10,
+            });
+            continue;
+        }
+
+        if !grounded {
+            if !abilities.double_jump || air_jump.0 {
+                continue;
+            }
+
+            // The second jump starts from a standstill instead of fighting the fall
+            air_jump.0 = true;
+            velocity.linvel.y = 0.;
+        }
+
+        match character_output {
+            Some(_) => velocity.linvel.y = jump_speed.0,
+            None => external_impulse.impulse = Vec2::new(0., jump_strength.0),
+        }
+        gameplay_writer.write(BPGameplayMessage::Jumped);
 
         particle_writer.write(BPSpawnDustMessage {
             position: Vec2::new(transform.translation.x, transform.translation.y - 25.0), // -25 is feet
@@ -152,13 +545,153 @@ fn jump_player(mut query: Query<(&ActionState<Action>,
     }
 }
 
+#[allow(clippy::type_complexity)]
+fn dash_player(mut query: Query<(&BPPlayerInput,
+                                 &BPPlayerIndex,
+                                 &BPPlayerDashControl,
+                                 &BPPlayerRunControl,
+                                 &BPPlayerContacts,
+                                 &BPPlayerController,
+                                 &Damping,
+                                 &Transform,
+                                 &BPAbilities,
+                                 &BPPlayerGroundPound,
+                                 &mut BPPlayerDash,
+                                 &mut GravityScale,
+                                 &mut CollisionGroups,
+                                 &mut Velocity), With<BPPlayerMarker>>,
+               mut afterimage_writer: MessageWriter<BPSpawnAfterimageMessage>,
+               time: Res<Time>) {
+    let delta = time.delta_secs();
+
+    for (input,
+        index,
+        dash_control,
+        run_control,
+        contacts,
+        controller,
+        damping,
+        transform,
+        abilities,
+        ground_pound,
+        mut dash,
+        mut gravity_scale,
+        mut collision_groups,
+        mut velocity) in &mut query {
+        dash.cooldown_left = (dash.cooldown_left - delta).max(0.);
+        if contacts.grounded && dash.air_dashes_used > 0 {
+            dash.air_dashes_used = 0;
+        }
+        if input.run != 0. {
+            dash.direction = input.run.signum();
+        }
+
+        let can_dash = abilities.dash
+            && !ground_pound.active
+            && !dash.is_dashing()
+            && dash.cooldown_left <= 0.
+            && (contacts.grounded || dash.air_dashes_used < dash_control.air_dashes);
+        if input.dash_pressed && can_dash {
+            if !contacts.grounded {
+                dash.air_dashes_used += 1;
+            }
+            dash.time_left = dash_control.duration;
+            dash.cooldown_left = dash_control.duration + dash_control.cooldown;
+        }
+
+        if dash.is_dashing() {
+            dash.time_left -= delta;
+
+            // Jumping out of a dash keeps the jump
+            if input.jump_pressed || !dash.is_dashing() {
+                dash.time_left = 0.;
+                gravity_scale.0 = dash_control.gravity_scale;
+                velocity.linvel.x = velocity.linvel.x.clamp(-run_control.max_speed, run_control.max_speed);
+            } else {
+                // Straight ahead, neither gravity nor damping get a say
+                gravity_scale.0 = 0.;
+                let speed = dash.direction * dash_control.speed;
+                velocity.linvel = match controller {
+                    BPPlayerController::Dynamic => Vec2::new(speed * (1. + delta * damping.linear_damping), 0.),
+                    BPPlayerController::Kinematic => Vec2::new(speed, 0.),
+                };
+
+                afterimage_writer.write(BPSpawnAfterimageMessage {
+                    position: transform.translation.xy(),
+                    player: index.0,
+                    scale: transform.scale.xy(),
+                });
+            }
+        }
+
+        // Enemies are left out of the collisions to be dashed through
+        let mut groups = *collision_groups;
+        groups.filters.set(PAWN_GROUP, !dash.passes_through_enemies(dash_control));
+        if *collision_groups != groups {
+            *collision_groups = groups;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn ground_pound_player(mut query: Query<(&BPPlayerInput,
+                                         &BPAbilities,
+                                         &BPPlayerContacts,
+                                         &BPPlayerDash,
+                                         &Transform,
+                                         &mut BPPlayerGroundPound,
+                                         &mut ExternalImpulse,
+                                         &mut Velocity), With<BPPlayerMarker>>,
+                       mut particle_writer: MessageWriter<BPSpawnDustMessage>,
+                       mut camera_shake: ResMut<BPCameraShake>) {
+    for (input,
+        abilities,
+        contacts,
+        dash,
+        transform,
+        mut ground_pound,
+        mut external_impulse,
+        mut velocity) in &mut query {
+        if ground_pound.active {
+            if contacts.grounded {
+                ground_pound.active = false;
+                camera_shake.add_trauma(0.3);
+                particle_writer.write(BPSpawnDustMessage {
+                    position: transform.translation.xy() - Vec2::new(0.0, 25.0), // Feet pos
+                    count: 15,
+                });
+            } else {
+                velocity.linvel = Vec2::new(0., -ground_pound.speed);
+            }
+            continue;
+        }
+
+        if input.down_pressed && abilities.ground_pound && !contacts.grounded && !dash.is_dashing() {
+            ground_pound.active = true;
+            external_impulse.impulse = Vec2::ZERO;
+            velocity.linvel = Vec2::new(0., -ground_pound.speed);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn handle_player_collision(mut commands: Commands,
                            mut collision_events: MessageReader<CollisionEvent>,
                            enemy_query: Query<&Transform, With<BPEnemyMarker>>,
                            world_query: Query<&Transform, With<BPLevelElement>>,
-                           mut player_query: Query<(&Transform, &mut ExternalImpulse), With<BPPlayerMarker>>,
+                           mut player_query: Query<(&Transform,
+                                                    &mut ExternalImpulse,
+                                                    &mut Velocity,
+                                                    &BPPlayerController,
+                                                    &BPPlayerDash,
+                                                    &BPPlayerDashControl), With<BPPlayerMarker>>,
                            mut next_state: ResMut<NextState<BPGameState>>,
-                           mut particle_writer: MessageWriter<BPSpawnDustMessage>) {
+                           mut particle_writer: MessageWriter<BPSpawnDustMessage>,
+                           mut camera_shake: ResMut<BPCameraShake>,
+                           mut gameplay_writer: MessageWriter<BPGameplayMessage>) {
+    let player_count = player_query.iter().count();
+    let mut dead_players: Vec<Entity> = Vec::new();
+
     for event in collision_events.read() {
         if let CollisionEvent::Started(e1, e2, _flags) = event {
             let mut player : Option<&Entity> = None;
@@ -189,30 +722,43 @@ fn handle_player_collision(mut commands: Commands,
                     world_transform = Some(t);
                 }
 
-                if let Some(_) = world_transform {
+                if world_transform.is_some() {
                     // Spawn dust particles
                     if let Ok(player_query) = player_query.get(*player) {
-                        let (transform, _) = player_query;
+                        let (transform, ..) = player_query;
                         particle_writer.write(BPSpawnDustMessage {
                             position: transform.translation.xy() - Vec2::new(0.0, 25.0), // Feet pos
                             count: 5,
                         });
+                        gameplay_writer.write(BPGameplayMessage::Landed);
                     }
                 }
             }
 
-            let Some(enemy) = enemy else { return; };
+            let Some(enemy) = enemy else { continue; };
 
-            if let Ok(mut player_query) = player_query.get_mut(*player) {
-                if let Ok(enemy_transform) = enemy_query.get(*enemy) {
-                    if player_query.0.translation.y > enemy_transform.translation.y + 20. {
-                        //Enemy dead
-                        commands.entity(*enemy).despawn();
-                        player_query.1.impulse = Vec2::new(0., 1000000.);
-                        next_state.set(BPGameState::Victory);
+            if let Ok(mut player_query) = player_query.get_mut(*player)
+                && let Ok(enemy_transform) = enemy_query.get(*enemy) {
+                if player_query.0.translation.y > enemy_transform.translation.y + 20. {
+                    //Enemy dead
+                    commands.entity(*enemy).despawn();
+                    match player_query.3 {
+                        BPPlayerController::Dynamic => player_query.1.impulse = Vec2::new(0., 1000000.),
+                        BPPlayerController::Kinematic => player_query.2.linvel.y = STOMP_BOUNCE_SPEED,
                     }
-                    else {
-                        commands.entity(*player).despawn();
+                    camera_shake.add_trauma(0.4);
+                    gameplay_writer.write(BPGameplayMessage::Stomped);
+                    gameplay_writer.write(BPGameplayMessage::Victory);
+                    next_state.set(BPGameState::Victory);
+                }
+                else if !dead_players.contains(player) && !player_query.4.passes_through_enemies(player_query.5) {
+                    commands.entity(*player).despawn();
+                    dead_players.push(*player);
+                    camera_shake.add_trauma(0.8);
+                    gameplay_writer.write(BPGameplayMessage::Died);
+
+                    // In co-op the game goes on as long as someone is still alive
+                    if player_count <= dead_players.len() {
                         next_state.set(BPGameState::GameOver);
                     }
                 }
@@ -222,10 +768,13 @@ fn handle_player_collision(mut commands: Commands,
 }
 
 fn procedural_animation_system(
-    mut query: Query<(&mut Transform, &Velocity), With<BPPlayerMarker>>,
+    mut query: Query<(&mut Transform, &Velocity, &BPPlayerContacts, &BPPlayerDash, &BPAbilities), With<BPPlayerMarker>>,
     time: Res<Time>,
 ) {
-    for (mut transform, velocity) in &mut query {
+    for (mut transform, velocity, contacts, dash, abilities) in &mut query {
+        // 0. IS SLIDING DOWN A WALL? -> Squashed against it, once it can wall jump
+        let wall = contacts.sliding_wall().filter(|_| abilities.wall_jump);
+
         // 1. IS JUMPING? (High vertical speed) -> Stretch
         let is_airborne = velocity.linvel.y.abs() > 50.0;
 
@@ -234,7 +783,17 @@ fn procedural_animation_system(
 
         let target_scale;
 
-        if is_airborne {
+        if dash.is_dashing() {
+            // --- DASH STRETCH ---
+            // Long and flat, along the dash
+            target_scale = Vec3::new(1.3, 0.75, 1.0);
+
+        } else if wall.is_some() {
+            // --- WALL SQUASH ---
+            // Flattened against the wall, and longer as it slides
+            target_scale = Vec3::new(0.8, 1.2, 1.0);
+
+        } else if is_airborne {
             // --- JUMP STRETCH (Existing Logic) ---
             let stretch_factor = 1.0 + (velocity.linvel.y.abs() / 2000.0);
             let clamped_y = stretch_factor.clamp(0.8, 1.5);
@@ -265,10 +824,62 @@ fn procedural_animation_system(
         // --- BONUS: TILT (The "Lean") ---
         // If you are a square, this looks great. If you are a circle, you won't see this
         // unless you add "eyes" or the waddle makes you oval enough to notice.
-        let lean_angle = (-velocity.linvel.x / 5000.0).clamp(-0.2, 0.2);
+        // On a wall, lean away from it instead
+        let lean_angle = match wall {
+            Some(side) => side * 0.15,
+            None => (-velocity.linvel.x / 5000.0).clamp(-0.2, 0.2),
+        };
         transform.rotation = transform.rotation.lerp(
             Quat::from_rotation_z(lean_angle),
             10.0 * time.delta().as_secs_f32()
         );
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One sixteenth of a second keeps every step exact
+    const DELTA: f32 = 0.0625;
+
+    fn run_control() -> BPPlayerRunControl {
+        BPPlayerRunControl {
+            max_speed: 400.,
+            acceleration: 1600.,
+            deceleration: 3200.,
+            air_control: 0.5,
+            turn_around_boost: 2.,
+        }
+    }
+
+    #[test]
+    fn accelerates_up_to_the_max_speed() {
+        assert_eq!(run_control().next_speed(1., 0., true, DELTA), 100.);
+        assert_eq!(run_control().next_speed(1., 350., true, DELTA), 400.);
+        assert_eq!(run_control().next_speed(-0.5, 0., true, DELTA), -100.);
+    }
+
+    #[test]
+    fn decelerates_when_letting_go() {
+        assert_eq!(run_control().next_speed(0., 300., true, DELTA), 100.);
+        assert_eq!(run_control().next_speed(0., -100., true, DELTA), 0.);
+    }
+
+    #[test]
+    fn decelerates_when_going_faster_than_asked() {
+        assert_eq!(run_control().next_speed(0.5, 400., true, DELTA), 200.);
+    }
+
+    #[test]
+    fn turning_around_is_boosted() {
+        assert_eq!(run_control().next_speed(-1., 100., true, DELTA), -100.);
+    }
+
+    #[test]
+    fn air_control_scales_every_rate() {
+        assert_eq!(run_control().next_speed(1., 0., false, DELTA), 50.);
+        assert_eq!(run_control().next_speed(0., 300., false, DELTA), 200.);
+        assert_eq!(run_control().next_speed(-1., 100., false, DELTA), 0.);
+    }
+}
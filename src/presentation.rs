@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use crate::abilities::{BPAbilityPickup, PICKUP_RADIUS};
+use crate::camera::BPCameraBundle;
+use crate::enemy::BPEnemyMarker;
+use crate::level::BPLevelElement;
+use crate::player::{player_color, player_shape, BPPlayerIndex, BPPlayerMarker};
+
+// Gameplay entities only carry logic and physics. The meshes and materials are attached here
+// when they appear, so the simulation runs without a renderer and the look can be swapped.
+pub struct BPPresentationPlugin;
+
+impl Plugin for BPPresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, setup_camera)
+            .add_systems(Update, (add_player_visuals, add_enemy_visuals, add_level_element_visuals, add_pickup_visuals));
+    }
+}
+
+fn setup_camera(mut commands: Commands) {
+    commands.spawn(BPCameraBundle::new(5.0));
+}
+
+fn add_player_visuals(mut commands: Commands,
+                      mut meshes: ResMut<Assets<Mesh>>,
+                      mut materials: ResMut<Assets<ColorMaterial>>,
+                      query: Query<(Entity, &BPPlayerIndex), Added<BPPlayerMarker>>) {
+    for (entity, index) in &query {
+        commands.entity(entity).insert((
+            Mesh2d(meshes.add(player_shape())),
+            MeshMaterial2d(materials.add(player_color(index.0))),
+        ));
+    }
+}
+
+fn add_enemy_visuals(mut commands: Commands,
+                     mut meshes: ResMut<Assets<Mesh>>,
+                     mut materials: ResMut<Assets<ColorMaterial>>,
+                     query: Query<Entity, Added<BPEnemyMarker>>) {
+    for entity in &query {
+        commands.entity(entity).insert((
+            Mesh2d(meshes.add(Rectangle::new(50., 50.))),
+            MeshMaterial2d(materials.add(Color::srgb(8.25, 2.4, 2.1))), // RGB values exceed 1 to achieve a bright color for the bloom effect
+        ));
+    }
+}
+
+/// Solid level elements are drawn as the box of their collider, sensors stay invisible.
+#[allow(clippy::type_complexity)]
+fn add_level_element_visuals(mut commands: Commands,
+                             mut meshes: ResMut<Assets<Mesh>>,
+                             mut materials: ResMut<Assets<ColorMaterial>>,
+                             query: Query<(Entity, &Collider), (Added<BPLevelElement>, Without<Sensor>)>) {
+    for (entity, collider) in &query {
+        let Some(cuboid) = collider.as_cuboid() else {
+            continue;
+        };
+
+        commands.entity(entity).insert((
+            Mesh2d(meshes.add(Rectangle::from_size(cuboid.half_extents() * 2.))),
+            MeshMaterial2d(materials.add(Color::srgb(34.0/255.0, 34.0/255.0, 34.0/255.0))),
+        ));
+    }
+}
+
+fn add_pickup_visuals(mut commands: Commands,
+                      mut meshes: ResMut<Assets<Mesh>>,
+                      mut materials: ResMut<Assets<ColorMaterial>>,
+                      query: Query<Entity, Added<BPAbilityPickup>>) {
+    for entity in &query {
+        commands.entity(entity).insert((
+            Mesh2d(meshes.add(Circle::new(PICKUP_RADIUS))),
+            MeshMaterial2d(materials.add(Color::srgb(6.0, 4.5, 0.8))),
+        ));
+    }
+}
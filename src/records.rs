@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const RECORDS_PATH: &str = "records.ron";
+
+pub struct BPRecordsPlugin;
+
+impl Plugin for BPRecordsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BPRecords::load());
+    }
+}
+
+/// Best run of every level, by `BPCurrentLevel` index.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct BPRecords {
+    pub levels: BTreeMap<usize, BPLevelRecord>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct BPLevelRecord {
+    /// Number of fixed ticks from the start of the level to the victory.
+    pub best_ticks: usize,
+    /// Position of the first player on every fixed tick of the best run, played back as a ghost.
+    pub ghost: Vec<Vec2>,
+}
+
+impl BPRecords {
+    /// Reads the records from disk, starting from scratch if the file is missing or broken.
+    pub fn load() -> Self {
+        let Ok(content) = std::fs::read_to_string(RECORDS_PATH) else {
+            return Self::default();
+        };
+
+        match ron::from_str(&content) {
+            Ok(records) => records,
+            Err(error) => {
+                warn!("Could not parse {RECORDS_PATH}, starting without records: {error}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let content = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(content) => content,
+            Err(error) => {
+                error!("Could not serialize records: {error}");
+                return;
+            }
+        };
+
+        if let Err(error) = std::fs::write(RECORDS_PATH, content) {
+            error!("Could not write {RECORDS_PATH}: {error}");
+        }
+    }
+
+    /// Keeps the run if it beats the best time of the level. Returns whether it did.
+    pub fn submit(&mut self, level: usize, ticks: usize, ghost: Vec<Vec2>) -> bool {
+        if self.levels.get(&level).is_some_and(|record| record.best_ticks <= ticks) {
+            return false;
+        }
+
+        self.levels.insert(level, BPLevelRecord { best_ticks: ticks, ghost });
+        true
+    }
+}
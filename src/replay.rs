@@ -0,0 +1,175 @@
+use std::path::{Path, PathBuf};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::abilities::BPAbilities;
+use crate::controller::BPPlayerController;
+use crate::input::{BPInputSystems, BPPlayerInput};
+use crate::level::BPCurrentLevel;
+use crate::player::{BPPlayerIndex, BPPlayerMarker, MAX_PLAYERS};
+use crate::rng::BPRng;
+use crate::save::BPSaveData;
+use crate::settings::BPSettings;
+use crate::state::BPGameState;
+use crate::tuning::BPPlayerTuning;
+
+/// Every run is recorded here, so a playtester can send it along with a bug report.
+pub const LAST_REPLAY_PATH: &str = "replays/last.replay.ron";
+
+pub struct BPReplayPlugin;
+
+impl Plugin for BPReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<BPReplayRecorder>()
+            .configure_sets(FixedPreUpdate, BPInputSystems::Sample.run_if(not(resource_exists::<BPReplayPlayback>)))
+            .add_systems(OnEnter(BPGameState::InGame), start_recording)
+            // Playing a replay back must not overwrite the file it comes from
+            .add_systems(OnExit(BPGameState::InGame), save_recording.run_if(not(resource_exists::<BPReplayPlayback>)))
+            .add_systems(FixedPreUpdate, (
+                drive_replay_input.run_if(resource_exists::<BPReplayPlayback>),
+                record_player_input,
+            )
+                .chain()
+                .after(BPInputSystems::Sample)
+                .run_if(in_state(BPGameState::InGame)));
+    }
+}
+
+/// The per-tick input of every player during one run, and what is needed to set the run up again.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BPReplay {
+    pub player_count: usize,
+    /// Replays recorded before it was stored play back with the default setting.
+    #[serde(default = "default_players_collide")]
+    pub players_collide: bool,
+    pub level: usize,
+    /// Seed of `BPRng` during the run. Replays recorded before it was stored play back with seed 0.
+    #[serde(default)]
+    pub seed: u64,
+    /// Controller of each player by `BPPlayerIndex`. Replays recorded before there was a choice used the dynamic one.
+    #[serde(default)]
+    pub player_controllers: [BPPlayerController; MAX_PLAYERS],
+    /// Abilities unlocked when the run started, those picked up during the run follow from the input.
+    #[serde(default)]
+    pub abilities: BPAbilities,
+    /// Player tuning during the run. Replays recorded before it was stored play back with the default tuning.
+    #[serde(default)]
+    pub tuning: BPPlayerTuning,
+    /// One entry per fixed tick, holding the input of each player by `BPPlayerIndex`.
+    pub ticks: Vec<Vec<BPPlayerInput>>,
+}
+
+fn default_players_collide() -> bool {
+    BPSettings::default().players_collide
+}
+
+impl BPReplay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+        ron::from_str(&content).map_err(|error| error.to_string())
+    }
+
+    pub fn save(&self, path: &Path) {
+        let content = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(content) => content,
+            Err(error) => {
+                error!("Could not serialize replay: {error}");
+                return;
+            }
+        };
+
+        if let Some(directory) = path.parent()
+            && let Err(error) = std::fs::create_dir_all(directory) {
+            error!("Could not create {}: {error}", directory.display());
+            return;
+        }
+
+        if let Err(error) = std::fs::write(path, content) {
+            error!("Could not write {}: {error}", path.display());
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct BPReplayRecorder(pub BPReplay);
+
+/// When present, player input comes from this replay instead of the devices.
+#[derive(Resource)]
+pub struct BPReplayPlayback {
+    pub replay: BPReplay,
+    pub tick: usize,
+}
+
+impl BPReplayPlayback {
+    pub fn from_file(path: PathBuf) -> Result<Self, String> {
+        let replay = BPReplay::load(&path)
+            .map_err(|error| format!("Could not load replay {}: {error}", path.display()))?;
+
+        Ok(Self { replay, tick: 0 })
+    }
+}
+
+fn start_recording(mut recorder: ResMut<BPReplayRecorder>,
+                   playback: Option<ResMut<BPReplayPlayback>>,
+                   settings: Res<BPSettings>,
+                   save_data: Res<BPSaveData>,
+                   tuning: Res<BPPlayerTuning>,
+                   current_level: Res<BPCurrentLevel>,
+                   rng: Res<BPRng>) {
+    let replay = playback.as_ref().map(|playback| &playback.replay);
+    let player_count = replay.map_or(settings.player_count, |replay| replay.player_count);
+    let players_collide = replay.map_or(settings.players_collide, |replay| replay.players_collide);
+    let player_controllers = replay.map_or(settings.player_controllers, |replay| replay.player_controllers);
+    let abilities = replay.map_or(save_data.abilities, |replay| replay.abilities);
+    let tuning = replay.map_or(&*tuning, |replay| &replay.tuning).clone();
+    recorder.0 = BPReplay {
+        player_count: player_count.clamp(1, MAX_PLAYERS),
+        players_collide,
+        level: current_level.0,
+        seed: rng.seed(),
+        player_controllers,
+        abilities,
+        tuning,
+        ticks: Vec::new(),
+    };
+
+    if let Some(mut playback) = playback {
+        playback.tick = 0;
+    }
+}
+
+fn save_recording(recorder: Res<BPReplayRecorder>) {
+    recorder.0.save(Path::new(LAST_REPLAY_PATH));
+}
+
+fn drive_replay_input(mut playback: ResMut<BPReplayPlayback>,
+                      mut player_query: Query<(&BPPlayerIndex, &mut BPPlayerInput)>) {
+    let tick = playback.tick;
+    let inputs = playback.replay.ticks.get(tick);
+
+    if tick == playback.replay.ticks.len() {
+        info!("Replay finished after {tick} ticks");
+    }
+
+    for (index, mut input) in &mut player_query {
+        // Once the replay is over players stand still
+        *input = inputs
+            .and_then(|inputs| inputs.get(index.0))
+            .copied()
+            .unwrap_or_default();
+    }
+
+    playback.tick += 1;
+}
+
+fn record_player_input(mut recorder: ResMut<BPReplayRecorder>,
+                       player_query: Query<(&BPPlayerIndex, &BPPlayerInput), With<BPPlayerMarker>>) {
+    let mut inputs = vec![BPPlayerInput::default(); recorder.0.player_count];
+    for (index, input) in &player_query {
+        if let Some(slot) = inputs.get_mut(index.0) {
+            *slot = *input;
+        }
+    }
+
+    recorder.0.ticks.push(inputs);
+}
@@ -0,0 +1,64 @@
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use crate::state::BPGameState;
+
+// All randomness goes through `BPRng` so a run can be reproduced from its seed.
+// Each system draws from its own stream, so adding a draw in one place does not shift the others.
+pub struct BPRngPlugin;
+
+impl Plugin for BPRngPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<BPRng>()
+            .add_systems(OnEnter(BPGameState::InGame), reset_rng);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum BPRngStream {
+    Movement,
+    Dust,
+    Audio,
+}
+
+/// The source of every random draw, reproducible from its seed. Streams restart from the seed when a run starts.
+#[derive(Resource, Debug)]
+pub struct BPRng {
+    seed: u64,
+    streams: HashMap<BPRngStream, StdRng>,
+}
+
+impl Default for BPRng {
+    fn default() -> Self {
+        Self::new(rand::random())
+    }
+}
+
+impl BPRng {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, streams: HashMap::default() }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The generator of one stream, forked from the seed the first time it is used.
+    pub fn stream(&mut self, stream: BPRngStream) -> &mut StdRng {
+        let seed = self.seed;
+        self.streams
+            .entry(stream)
+            .or_insert_with(|| StdRng::seed_from_u64(seed ^ (stream as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)))
+    }
+
+    /// Restarts every stream from the seed.
+    pub fn reset(&mut self) {
+        self.streams.clear();
+    }
+}
+
+fn reset_rng(mut rng: ResMut<BPRng>) {
+    rng.reset();
+}
@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::abilities::BPAbilities;
+
+pub const SAVE_PATH: &str = "save.ron";
+
+// Progress kept from one session to the next. Written as soon as it changes.
+pub struct BPSavePlugin;
+
+impl Plugin for BPSavePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<BPSaveData>()
+            .insert_resource(BPSaveData::load())
+            .add_systems(Update, write_save.run_if(resource_changed::<BPSaveData>.and(not(resource_added::<BPSaveData>))));
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default, Reflect)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct BPSaveData {
+    /// Unlocked by pickups, shared by every player.
+    pub abilities: BPAbilities,
+}
+
+impl BPSaveData {
+    /// Reads the save from disk, starting from scratch if the file is missing or broken.
+    pub fn load() -> Self {
+        let Ok(content) = std::fs::read_to_string(SAVE_PATH) else {
+            return Self::default();
+        };
+
+        match ron::from_str(&content) {
+            Ok(save) => save,
+            Err(error) => {
+                warn!("Could not parse {SAVE_PATH}, starting a new save: {error}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let content = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(content) => content,
+            Err(error) => {
+                error!("Could not serialize save: {error}");
+                return;
+            }
+        };
+
+        if let Err(error) = std::fs::write(SAVE_PATH, content) {
+            error!("Could not write {SAVE_PATH}: {error}");
+        }
+    }
+}
+
+fn write_save(save_data: Res<BPSaveData>) {
+    save_data.save();
+}
@@ -0,0 +1,155 @@
+use bevy::prelude::*;
+use bevy::window::{MonitorSelection, PresentMode, PrimaryWindow, VideoModeSelection, WindowMode};
+use serde::{Deserialize, Serialize};
+use crate::controller::BPPlayerController;
+use crate::player::MAX_PLAYERS;
+
+pub const SETTINGS_PATH: &str = "settings.ron";
+
+pub struct BPSettingsPlugin;
+
+impl Plugin for BPSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<BPSettings>()
+            .init_resource::<BPSettings>()
+            .add_systems(Update, apply_window_settings.run_if(resource_changed::<BPSettings>));
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum BPWindowMode {
+    #[default]
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl BPWindowMode {
+    pub fn next(self) -> Self {
+        match self {
+            BPWindowMode::Windowed => BPWindowMode::Borderless,
+            BPWindowMode::Borderless => BPWindowMode::Fullscreen,
+            BPWindowMode::Fullscreen => BPWindowMode::Windowed,
+        }
+    }
+
+    pub fn previous(self) -> Self {
+        self.next().next()
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BPWindowMode::Windowed => "Windowed",
+            BPWindowMode::Borderless => "Borderless",
+            BPWindowMode::Fullscreen => "Fullscreen",
+        }
+    }
+
+    fn to_window_mode(self) -> WindowMode {
+        match self {
+            BPWindowMode::Windowed => WindowMode::Windowed,
+            BPWindowMode::Borderless => WindowMode::BorderlessFullscreen(MonitorSelection::Current),
+            BPWindowMode::Fullscreen => WindowMode::Fullscreen(MonitorSelection::Current, VideoModeSelection::Current),
+        }
+    }
+}
+
+/// Everything the player can tweak from the options screen.
+/// Volumes and multipliers are stored in the 0..=1 range.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct BPSettings {
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub window_mode: BPWindowMode,
+    pub vsync: bool,
+    pub screen_shake: f32,
+    pub particle_density: f32,
+    /// Number of local players spawned when a game starts.
+    pub player_count: usize,
+    pub players_collide: bool,
+    /// Physics driving each player, by `BPPlayerIndex`, to compare how they feel.
+    pub player_controllers: [BPPlayerController; MAX_PLAYERS],
+}
+
+impl Default for BPSettings {
+    fn default() -> Self {
+        Self {
+            master_volume: 0.8,
+            music_volume: 0.6,
+            sfx_volume: 0.8,
+            window_mode: BPWindowMode::Windowed,
+            vsync: true,
+            screen_shake: 1.0,
+            particle_density: 1.0,
+            player_count: 1,
+            players_collide: true,
+            player_controllers: [BPPlayerController::Dynamic; MAX_PLAYERS],
+        }
+    }
+}
+
+impl BPSettings {
+    /// Reads the settings from disk, falling back to the defaults if the file is missing or broken.
+    pub fn load() -> Self {
+        let Ok(content) = std::fs::read_to_string(SETTINGS_PATH) else {
+            return Self::default();
+        };
+
+        match ron::from_str(&content) {
+            Ok(settings) => settings,
+            Err(error) => {
+                warn!("Could not parse {SETTINGS_PATH}, using default settings: {error}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let content = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(content) => content,
+            Err(error) => {
+                error!("Could not serialize settings: {error}");
+                return;
+            }
+        };
+
+        if let Err(error) = std::fs::write(SETTINGS_PATH, content) {
+            error!("Could not write {SETTINGS_PATH}: {error}");
+        }
+    }
+
+    /// The primary window, configured from the settings. Used when building `WindowPlugin`.
+    pub fn window(&self) -> Window {
+        Window {
+            title: "Bevy Platformer".into(),
+            mode: self.window_mode.to_window_mode(),
+            present_mode: self.present_mode(),
+            ..default()
+        }
+    }
+
+    fn present_mode(&self) -> PresentMode {
+        if self.vsync { PresentMode::AutoVsync } else { PresentMode::AutoNoVsync }
+    }
+}
+
+fn apply_window_settings(settings: Res<BPSettings>,
+                         mut window_query: Query<&mut Window, With<PrimaryWindow>>) {
+    let Ok(mut window) = window_query.single_mut() else {
+        return;
+    };
+
+    let mode = settings.window_mode.to_window_mode();
+    if window.mode != mode {
+        window.mode = mode;
+    }
+
+    let present_mode = settings.present_mode();
+    if window.present_mode != present_mode {
+        window.present_mode = present_mode;
+    }
+}
@@ -1,9 +1,35 @@
 use bevy::prelude::*;
 
+/// Registers the game states. Needs the `StatesPlugin`, which `DefaultPlugins` and `MinimalPlugins` setups provide.
+#[derive(Default)]
+pub struct BPStatePlugin {
+    /// State the game starts in, `InGame` unless testing the other screens.
+    pub initial_state: BPGameState,
+}
+
+impl Plugin for BPStatePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_state(self.initial_state)
+            .init_state::<BPMenuState>();
+    }
+}
+
 #[derive(States, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
 pub enum BPGameState {
     #[default]
     InGame,
     GameOver,
     Victory,
-}
\ No newline at end of file
+    /// The level editor of the debug tools. The level is not simulated.
+    Editor,
+}
+
+/// Overlay menus that can be opened on top of any `BPGameState`. The game is paused while one is open.
+#[derive(States, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub enum BPMenuState {
+    #[default]
+    Closed,
+    Options,
+    Controls,
+}
@@ -0,0 +1,243 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::hot_reload::ASSETS_DIRECTORY;
+use crate::replay::BPReplayPlayback;
+use crate::controller::apply_character_tuning;
+use crate::player::{BPPlayerDashControl, BPPlayerGroundPound, BPPlayerJumpSpeed, BPPlayerJumpStrength, BPPlayerMarker, BPPlayerRunControl, BPPlayerWallControl};
+
+/// Presets of the player tuning, relative to the assets.
+pub const PLAYER_TUNING_PATH: &str = "tuning/player.presets.ron";
+
+// The values that define how the players move. They are read from `PLAYER_TUNING_PATH`,
+// can be edited live from the inspector, and are applied to the players already spawned when they change.
+pub struct BPTuningPlugin;
+
+impl Plugin for BPTuningPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<BPPlayerTuning>()
+            .register_type::<BPPlayerTuningPresets>()
+            // A replay keeps the tuning it was recorded with
+            .add_systems(Update, apply_player_tuning.run_if(resource_changed::<BPPlayerTuning>.and(not(resource_exists::<BPReplayPlayback>))));
+    }
+
+    fn finish(&self, app: &mut App) {
+        // Players are spawned before `Startup`, whoever builds the app can provide the tuning instead
+        if !app.world().contains_resource::<BPPlayerTuningPresets>() {
+            app.insert_resource(BPPlayerTuningPresets::load());
+        }
+
+        if !app.world().contains_resource::<BPPlayerTuning>() {
+            let tuning = app.world().resource::<BPPlayerTuningPresets>().active_tuning();
+            app.insert_resource(tuning);
+        }
+    }
+}
+
+/// How the players move. Distances are in world units and durations in seconds, like the rest of the physics.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct BPPlayerTuning {
+    pub jump_strength: f32,
+    /// Only shapes the fall, running sets the horizontal speed on its own.
+    pub linear_damping: f32,
+    pub gravity_scale: f32,
+    pub restitution: f32,
+    /// Top running speed on the ground.
+    pub max_speed: f32,
+    /// How fast the running speed builds up, per second.
+    pub acceleration: f32,
+    /// How fast the running speed drops when letting go, per second.
+    pub deceleration: f32,
+    /// Share of the acceleration and deceleration kept in the air, from 0 to 1.
+    pub air_control: f32,
+    /// Acceleration multiplier when pushing against the current direction.
+    pub turn_around_boost: f32,
+    /// Upward speed given by a jump with the kinematic controller, which has no mass to push.
+    pub jump_speed: f32,
+    /// Steepest slope the kinematic controller walks up, in degrees.
+    pub max_slope_angle: f32,
+    /// Highest step the kinematic controller climbs without jumping, 0 to disable.
+    pub step_height: f32,
+    /// How far down the kinematic controller looks for ground to stick to, 0 to disable.
+    pub snap_to_ground: f32,
+    /// Fastest fall while sliding down a wall.
+    pub wall_slide_speed: f32,
+    /// Velocity given by a wall jump, x away from the wall and y up. It replaces the current velocity.
+    pub wall_jump_impulse: Vec2,
+    /// How long the run input is ignored after a wall jump.
+    pub wall_jump_lockout: f32,
+    /// Horizontal speed of a dash, held for its whole duration.
+    pub dash_speed: f32,
+    pub dash_duration: f32,
+    /// Wait between the end of a dash and the next one.
+    pub dash_cooldown: f32,
+    /// Dashes allowed before landing again.
+    pub air_dashes: u32,
+    /// Dashing players go through enemies instead of dying on them.
+    pub dash_through_enemies: bool,
+    /// Falling speed of a ground pound.
+    pub ground_pound_speed: f32,
+}
+
+impl Default for BPPlayerTuning {
+    fn default() -> Self {
+        Self {
+            jump_strength: 2500000.,
+            linear_damping: 5.,
+            gravity_scale: 3.,
+            restitution: 0.1,
+            max_speed: 500.,
+            acceleration: 3000.,
+            deceleration: 4000.,
+            air_control: 0.6,
+            turn_around_boost: 2.,
+            jump_speed: 1100.,
+            max_slope_angle: 45.,
+            step_height: 15.,
+            snap_to_ground: 10.,
+            wall_slide_speed: 150.,
+            wall_jump_impulse: Vec2::new(450., 900.),
+            wall_jump_lockout: 0.2,
+            dash_speed: 1200.,
+            dash_duration: 0.15,
+            dash_cooldown: 0.4,
+            air_dashes: 1,
+            dash_through_enemies: false,
+            ground_pound_speed: 1500.,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Reflect)]
+pub struct BPPlayerTuningPreset {
+    pub name: String,
+    pub tuning: BPPlayerTuning,
+}
+
+/// Every tuning to compare, as stored in `PLAYER_TUNING_PATH`. `BPPlayerTuning` starts as the active one.
+#[derive(Resource, Asset, Serialize, Deserialize, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct BPPlayerTuningPresets {
+    pub presets: Vec<BPPlayerTuningPreset>,
+    /// Index in `presets`.
+    pub active: usize,
+}
+
+impl Default for BPPlayerTuningPresets {
+    fn default() -> Self {
+        let tuning = BPPlayerTuning::default();
+
+        Self {
+            presets: vec![
+                BPPlayerTuningPreset {
+                    name: "Default".to_string(),
+                    tuning: tuning.clone(),
+                },
+                BPPlayerTuningPreset {
+                    name: "Floaty".to_string(),
+                    tuning: BPPlayerTuning {
+                        jump_strength: 2000000.,
+                        gravity_scale: 2.,
+                        linear_damping: 3.,
+                        acceleration: 2000.,
+                        deceleration: 1500.,
+                        air_control: 0.9,
+                        ..tuning.clone()
+                    },
+                },
+                BPPlayerTuningPreset {
+                    name: "Heavy".to_string(),
+                    tuning: BPPlayerTuning {
+                        jump_strength: 3000000.,
+                        gravity_scale: 4.5,
+                        linear_damping: 7.,
+                        max_speed: 400.,
+                        acceleration: 1500.,
+                        deceleration: 6000.,
+                        air_control: 0.3,
+                        turn_around_boost: 3.,
+                        ..tuning
+                    },
+                },
+            ],
+            active: 0,
+        }
+    }
+}
+
+impl BPPlayerTuningPresets {
+    /// Reads the presets from disk, falling back to the defaults if the file is missing or broken.
+    pub fn load() -> Self {
+        let path = std::path::Path::new(ASSETS_DIRECTORY).join(PLAYER_TUNING_PATH);
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+
+        match ron::from_str(&content) {
+            Ok(presets) => presets,
+            Err(error) => {
+                warn!("Could not parse {}, using the default tuning: {error}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    pub fn active_preset(&self) -> Option<&BPPlayerTuningPreset> {
+        self.presets.get(self.active)
+    }
+
+    pub fn active_tuning(&self) -> BPPlayerTuning {
+        self.active_preset()
+            .map(|preset| preset.tuning.clone())
+            .unwrap_or_default()
+    }
+
+    /// Makes the next preset active, looping back to the first one.
+    pub fn cycle(&mut self) {
+        if !self.presets.is_empty() {
+            self.active = (self.active + 1) % self.presets.len();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn apply_player_tuning(tuning: Res<BPPlayerTuning>,
+                       mut query: Query<(&mut BPPlayerJumpStrength,
+                                         &mut BPPlayerJumpSpeed,
+                                         &mut BPPlayerRunControl,
+                                         &mut BPPlayerWallControl,
+                                         &mut BPPlayerDashControl,
+                                         &mut BPPlayerGroundPound,
+                                         &mut Damping,
+                                         &mut GravityScale,
+                                         &mut Restitution,
+                                         Option<&mut KinematicCharacterController>), With<BPPlayerMarker>>) {
+    for (mut jump_strength,
+        mut jump_speed,
+        mut run_control,
+        mut wall_control,
+        mut dash_control,
+        mut ground_pound,
+        mut damping,
+        mut gravity_scale,
+        mut restitution,
+        character_controller) in &mut query {
+        jump_strength.0 = tuning.jump_strength;
+        jump_speed.0 = tuning.jump_speed;
+        *run_control = BPPlayerRunControl::from_tuning(&tuning);
+        *wall_control = BPPlayerWallControl::from_tuning(&tuning);
+        *dash_control = BPPlayerDashControl::from_tuning(&tuning);
+        ground_pound.speed = tuning.ground_pound_speed;
+        damping.linear_damping = tuning.linear_damping;
+        gravity_scale.0 = tuning.gravity_scale;
+        restitution.coefficient = tuning.restitution;
+
+        if let Some(mut character_controller) = character_controller {
+            apply_character_tuning(&mut character_controller, &tuning);
+        }
+    }
+}
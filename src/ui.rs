@@ -1,6 +1,11 @@
 use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
-use crate::state::BPGameState;
+use serde::{Deserialize, Serialize};
+use crate::controls::{BPBindingSlot, BPControls};
+use crate::ghost::BPRunRecorder;
+use crate::level::BPCurrentLevel;
+use crate::records::BPRecords;
+use crate::state::{BPGameState, BPMenuState};
 
 pub struct BPUIPlugin;
 
@@ -13,23 +18,33 @@ impl Plugin for BPUIPlugin {
                 .add_systems(OnEnter(BPGameState::Victory), setup_victory_ui)
                 .add_systems(OnExit(BPGameState::Victory), cleanup_victory_ui)
                 .add_systems(Update, ui_update.run_if(in_state(BPGameState::GameOver)
-                                                        .or(in_state(BPGameState::Victory))));
+                                                        .or(in_state(BPGameState::Victory))
+                                                        .and(in_state(BPMenuState::Closed))));
     }
 }
 
 #[derive(Component)]
 pub struct BPUiGameOverMarker;
 
-#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect)]
+#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect, Serialize, Deserialize)]
 pub enum BPGameOverUiAction {
     Restart,
     Quit,
 }
 
-fn setup_game_over_ui(mut commands: Commands){
-    let input_map = InputMap::default()
-        .with(BPGameOverUiAction::Restart, KeyCode::KeyR)
-        .with(BPGameOverUiAction::Quit, KeyCode::KeyQ);
+/// Follows the bindings of the controls screen.
+fn restart_prompt(controls: &BPControls) -> String {
+    format!(
+        "Press {} to restart, {} to quit or Escape for options",
+        BPBindingSlot::Restart.inputs_label(controls, " or "),
+        BPBindingSlot::Quit.inputs_label(controls, " or "),
+    )
+}
+
+fn setup_game_over_ui(mut commands: Commands,
+                    controls: Res<BPControls>){
+    let input_map = controls.game_over_ui.clone();
+    let prompt = restart_prompt(&controls);
 
     commands.spawn((
         BPUiGameOverMarker,
@@ -54,7 +69,7 @@ fn setup_game_over_ui(mut commands: Commands){
             TextColor(Color::srgb(1.0, 0.0, 0.0)),
         ));
         parent.spawn((
-            Text::new("Press R to restart or Q to quit"),
+            Text::new(prompt),
             TextFont::from_font_size(40.0),
             TextColor(Color::srgb(1.0, 0.0, 0.0)),
         ));
@@ -68,10 +83,18 @@ fn cleanup_game_over_ui(mut commands: Commands,
     }
 }
 
-fn setup_victory_ui(mut commands: Commands){
-    let input_map = InputMap::default()
-        .with(BPGameOverUiAction::Restart, KeyCode::KeyR)
-        .with(BPGameOverUiAction::Quit, KeyCode::KeyQ);
+fn setup_victory_ui(mut commands: Commands,
+                    controls: Res<BPControls>,
+                    recorder: Res<BPRunRecorder>,
+                    records: Res<BPRecords>,
+                    current_level: Res<BPCurrentLevel>,
+                    fixed_time: Res<Time<Fixed>>){
+    let input_map = controls.game_over_ui.clone();
+    let prompt = restart_prompt(&controls);
+
+    let tick_secs = fixed_time.timestep().as_secs_f32();
+    let best_ticks = records.levels.get(&current_level.0).map_or(recorder.ticks, |record| record.best_ticks);
+    let times = format!("Time: {:.2}s   Best: {:.2}s", recorder.ticks as f32 * tick_secs, best_ticks as f32 * tick_secs);
 
     commands.spawn((
         BPUiGameOverMarker,
@@ -96,7 +119,12 @@ fn setup_victory_ui(mut commands: Commands){
             TextColor(Color::srgb(0.0, 1.0, 0.0)),
         ));
         parent.spawn((
-            Text::new("Press R to restart or Q to quit"),
+            Text::new(times),
+            TextFont::from_font_size(40.0),
+            TextColor(Color::srgb(0.0, 1.0, 0.0)),
+        ));
+        parent.spawn((
+            Text::new(prompt),
             TextFont::from_font_size(40.0),
             TextColor(Color::srgb(0.0, 1.0, 0.0)),
         ));
@@ -0,0 +1,371 @@
+use bevy::prelude::*;
+use bevy_platformer::abilities::{BPAbilities, BPAbility, BPAbilityPickup};
+use bevy_platformer::controller::BPPlayerController;
+use bevy_platformer::enemy::BPEnemyMarker;
+use bevy_platformer::headless::{BPHeadlessPlugin, BPScriptedInput};
+use bevy_platformer::input::BPPlayerInput;
+use bevy_platformer::level::{BPLevelData, BPLevelElementData, BPLevelElementKind};
+use bevy_platformer::platform::{BPEasing, BPPathMode, BPPlatformPath};
+use bevy_platformer::player::BPPlayerMarker;
+use bevy_platformer::replay::{BPReplay, BPReplayPlayback, BPReplayPlugin, BPReplayRecorder};
+use bevy_platformer::save::BPSaveData;
+use bevy_platformer::settings::BPSettings;
+use bevy_platformer::state::BPGameState;
+
+fn headless_app() -> App {
+    headless_app_with_level(BPLevelData::default())
+}
+
+fn headless_app_with_level(level_data: BPLevelData) -> App {
+    let mut app = App::new();
+    app.insert_resource(level_data);
+    app.add_plugins(BPHeadlessPlugin { seed: 0, ..default() });
+    app.finish();
+    app.cleanup();
+    // The first update enters the level and runs the startup systems
+    app.update();
+    app
+}
+
+/// A headless app that records its run, or plays `playback` back.
+fn headless_app_with_replay(seed: u64, playback: Option<BPReplayPlayback>) -> App {
+    let mut app = App::new();
+    app.insert_resource(BPLevelData::default());
+    if let Some(playback) = playback {
+        app.insert_resource(playback);
+    }
+    app.add_plugins((BPHeadlessPlugin { seed, ..default() }, BPReplayPlugin));
+    app.finish();
+    app.cleanup();
+    app.update();
+    app
+}
+
+fn step(app: &mut App, ticks: usize) {
+    for _ in 0..ticks {
+        app.update();
+    }
+}
+
+/// Holds a button of the first player for a single tick, like a press seen by `BPInputLatch`.
+fn press(app: &mut App, button: impl Fn(&mut BPPlayerInput) -> &mut bool) {
+    *button(&mut app.world_mut().resource_mut::<BPScriptedInput>().0[0]) = true;
+    step(app, 1);
+    *button(&mut app.world_mut().resource_mut::<BPScriptedInput>().0[0]) = false;
+}
+
+fn game_state(app: &App) -> BPGameState {
+    *app.world().resource::<State<BPGameState>>().get()
+}
+
+fn player_position(app: &mut App) -> Vec2 {
+    app.world_mut()
+        .query_filtered::<&Transform, With<BPPlayerMarker>>()
+        .single(app.world())
+        .unwrap()
+        .translation
+        .xy()
+}
+
+fn place_player(app: &mut App, position: Vec2) {
+    let mut transform = app.world_mut()
+        .query_filtered::<&mut Transform, With<BPPlayerMarker>>()
+        .single_mut(app.world_mut())
+        .unwrap();
+    transform.translation = position.extend(transform.translation.z);
+}
+
+/// Unlocks abilities as a pickup would, players get them on the next update.
+fn unlock_abilities(app: &mut App, abilities: &[BPAbility]) {
+    let mut save_data = app.world_mut().resource_mut::<BPSaveData>();
+    for ability in abilities {
+        save_data.abilities.unlock(*ability);
+    }
+}
+
+fn jump_peak(app: &mut App, ticks: usize) -> f32 {
+    let mut peak = player_position(app).y;
+    for _ in 0..ticks {
+        step(app, 1);
+        peak = peak.max(player_position(app).y);
+    }
+    peak
+}
+
+fn enemy_position(app: &mut App) -> Vec2 {
+    app.world_mut()
+        .query_filtered::<&Transform, With<BPEnemyMarker>>()
+        .single(app.world())
+        .unwrap()
+        .translation
+        .xy()
+}
+
+#[test]
+fn level_spawns_one_player_and_one_enemy() {
+    let mut app = headless_app();
+
+    assert_eq!(game_state(&app), BPGameState::InGame);
+    assert_eq!(app.world_mut().query::<&BPPlayerMarker>().iter(app.world()).count(), 1);
+    assert_eq!(app.world_mut().query::<&BPEnemyMarker>().iter(app.world()).count(), 1);
+}
+
+#[test]
+fn running_moves_the_player() {
+    let mut app = headless_app();
+    place_player(&mut app, Vec2::new(-400., -150.));
+    step(&mut app, 30);
+    let start = player_position(&mut app);
+
+    app.world_mut().resource_mut::<BPScriptedInput>().0[0].run = -1.;
+    step(&mut app, 30);
+
+    assert!(player_position(&mut app).x < start.x - 50.);
+}
+
+#[test]
+fn jumping_lifts_the_player() {
+    let mut app = headless_app();
+    place_player(&mut app, Vec2::new(-400., -150.));
+    step(&mut app, 60);
+    let start = player_position(&mut app);
+
+    press(&mut app, |input| &mut input.jump_pressed);
+    step(&mut app, 10);
+
+    assert!(player_position(&mut app).y > start.y + 50.);
+}
+
+#[test]
+fn kinematic_player_lands_and_jumps() {
+    let mut app = headless_app();
+    app.world_mut().resource_mut::<BPSettings>().player_controllers[0] = BPPlayerController::Kinematic;
+    place_player(&mut app, Vec2::new(-400., -100.));
+    step(&mut app, 60);
+    let start = player_position(&mut app);
+
+    press(&mut app, |input| &mut input.jump_pressed);
+    step(&mut app, 10);
+
+    assert!(player_position(&mut app).y > start.y + 50.);
+}
+
+#[test]
+fn one_way_platform_holds_the_player_until_it_drops() {
+    let mut app = headless_app_with_level(BPLevelData {
+        elements: vec![
+            BPLevelElementData {
+                kind: BPLevelElementKind::Platform,
+                position: Vec2::new(0., -200.),
+                half_size: Vec2::new(750., 25.),
+                path: None,
+            },
+            BPLevelElementData {
+                kind: BPLevelElementKind::OneWayPlatform,
+                position: Vec2::new(-400., -50.),
+                half_size: Vec2::new(100., 10.),
+                path: None,
+            },
+        ],
+    });
+    place_player(&mut app, Vec2::new(-400., 50.));
+    step(&mut app, 60);
+    assert!(player_position(&mut app).y > -40.);
+
+    app.world_mut().resource_mut::<BPScriptedInput>().0[0].down_held = true;
+    press(&mut app, |input| &mut input.jump_pressed);
+    app.world_mut().resource_mut::<BPScriptedInput>().0[0].down_held = false;
+    step(&mut app, 60);
+
+    assert!(player_position(&mut app).y < -100.);
+}
+
+#[test]
+fn moving_platform_carries_the_player() {
+    let mut app = headless_app_with_level(BPLevelData {
+        elements: vec![BPLevelElementData {
+            kind: BPLevelElementKind::Platform,
+            position: Vec2::new(-400., -50.),
+            half_size: Vec2::new(100., 10.),
+            path: Some(BPPlatformPath {
+                waypoints: vec![Vec2::new(200., 0.)],
+                mode: BPPathMode::Linear,
+                easing: BPEasing::Linear,
+                speed: 100.,
+                pause: 0.,
+            }),
+        }],
+    });
+    place_player(&mut app, Vec2::new(-400., 0.));
+
+    step(&mut app, 180);
+
+    let position = player_position(&mut app);
+    assert!(position.x > -250.);
+    assert!(position.y > -40.);
+}
+
+#[test]
+fn wall_jump_pushes_away_from_the_wall() {
+    let mut app = headless_app_with_level(BPLevelData {
+        elements: vec![
+            BPLevelElementData {
+                kind: BPLevelElementKind::Platform,
+                position: Vec2::new(0., -200.),
+                half_size: Vec2::new(750., 25.),
+                path: None,
+            },
+            BPLevelElementData {
+                kind: BPLevelElementKind::Platform,
+                position: Vec2::new(-600., 200.),
+                half_size: Vec2::new(25., 400.),
+                path: None,
+            },
+        ],
+    });
+    unlock_abilities(&mut app, &[BPAbility::WallJump]);
+    place_player(&mut app, Vec2::new(-550., 300.));
+    app.world_mut().resource_mut::<BPScriptedInput>().0[0].run = -1.;
+    step(&mut app, 20);
+    let start = player_position(&mut app);
+
+    press(&mut app, |input| &mut input.jump_pressed);
+    step(&mut app, 10);
+
+    let position = player_position(&mut app);
+    assert!(position.x > start.x + 30.);
+    assert!(position.y > start.y);
+}
+
+#[test]
+fn dashing_bursts_ahead_then_cools_down() {
+    let mut app = headless_app();
+    unlock_abilities(&mut app, &[BPAbility::Dash]);
+    place_player(&mut app, Vec2::new(-400., -150.));
+    step(&mut app, 30);
+    let start = player_position(&mut app);
+
+    press(&mut app, |input| &mut input.dash_pressed);
+    step(&mut app, 9);
+    let after_dash = player_position(&mut app);
+    assert!(after_dash.x > start.x + 120.);
+
+    // Still cooling down, the second press does nothing
+    press(&mut app, |input| &mut input.dash_pressed);
+    step(&mut app, 9);
+    assert!(player_position(&mut app).x < after_dash.x + 60.);
+}
+
+#[test]
+fn touching_a_pickup_unlocks_its_ability() {
+    let mut app = headless_app_with_level(BPLevelData {
+        elements: vec![
+            BPLevelElementData {
+                kind: BPLevelElementKind::Platform,
+                position: Vec2::new(0., -200.),
+                half_size: Vec2::new(750., 25.),
+                path: None,
+            },
+            BPLevelElementData {
+                kind: BPLevelElementKind::AbilityPickup(BPAbility::DoubleJump),
+                position: Vec2::new(-300., -150.),
+                half_size: Vec2::splat(15.),
+                path: None,
+            },
+        ],
+    });
+    place_player(&mut app, Vec2::new(-400., -150.));
+    app.world_mut().resource_mut::<BPScriptedInput>().0[0].run = 1.;
+    step(&mut app, 60);
+
+    let save_data = app.world().resource::<BPSaveData>();
+    assert_eq!(save_data.abilities, BPAbilities { double_jump: true, ..default() });
+    let pickups = app.world_mut().query::<&BPAbilityPickup>().iter(app.world()).count();
+    assert_eq!(pickups, 0);
+}
+
+#[test]
+fn double_jump_only_works_once_unlocked() {
+    let mut app = headless_app();
+    place_player(&mut app, Vec2::new(-400., -150.));
+    step(&mut app, 60);
+
+    // Jumping again near the top of the first jump
+    let double_jump_peak = |app: &mut App| {
+        press(app, |input| &mut input.jump_pressed);
+        step(app, 20);
+        press(app, |input| &mut input.jump_pressed);
+        let peak = jump_peak(app, 40);
+        step(app, 60);
+        peak
+    };
+    let locked_peak = double_jump_peak(&mut app);
+
+    unlock_abilities(&mut app, &[BPAbility::DoubleJump]);
+    step(&mut app, 1);
+    let unlocked_peak = double_jump_peak(&mut app);
+
+    assert!(unlocked_peak > locked_peak + 50.);
+}
+
+#[test]
+fn stomping_the_enemy_is_a_victory() {
+    let mut app = headless_app();
+    let enemy = enemy_position(&mut app);
+    place_player(&mut app, enemy + Vec2::new(0., 100.));
+
+    step(&mut app, 120);
+
+    assert_eq!(game_state(&app), BPGameState::Victory);
+}
+
+#[test]
+fn running_into_the_enemy_is_a_game_over() {
+    let mut app = headless_app();
+    let enemy = enemy_position(&mut app);
+    place_player(&mut app, Vec2::new(enemy.x + 300., enemy.y));
+
+    app.world_mut().resource_mut::<BPScriptedInput>().0[0].run = -1.;
+    step(&mut app, 240);
+
+    assert_eq!(game_state(&app), BPGameState::GameOver);
+}
+
+#[test]
+fn same_seed_and_input_give_the_same_run() {
+    let run = || {
+        let mut app = headless_app();
+        app.world_mut().resource_mut::<BPScriptedInput>().0[0].run = 1.;
+        step(&mut app, 90);
+        (player_position(&mut app), enemy_position(&mut app))
+    };
+
+    assert_eq!(run(), run());
+}
+
+#[test]
+fn a_replay_read_back_from_ron_plays_the_same_run() {
+    // Away from the enemy
+    let mut app = headless_app_with_replay(7, None);
+    app.world_mut().resource_mut::<BPScriptedInput>().0[0].run = -1.;
+    step(&mut app, 30);
+    press(&mut app, |input| &mut input.jump_pressed);
+    step(&mut app, 20);
+    press(&mut app, |input| &mut input.dash_pressed);
+    app.world_mut().resource_mut::<BPScriptedInput>().0[0].run = 0.;
+    step(&mut app, 40);
+    assert_eq!(game_state(&app), BPGameState::InGame);
+    let recorded = (player_position(&mut app), enemy_position(&mut app));
+
+    let content = ron::ser::to_string(&app.world().resource::<BPReplayRecorder>().0).unwrap();
+    let replay: BPReplay = ron::from_str(&content).unwrap();
+    let ticks = replay.ticks.len();
+
+    let mut app = headless_app_with_replay(replay.seed, Some(BPReplayPlayback { replay, tick: 0 }));
+    while app.world().resource::<BPReplayPlayback>().tick < ticks {
+        step(&mut app, 1);
+    }
+
+    assert_eq!(game_state(&app), BPGameState::InGame);
+    assert_eq!((player_position(&mut app), enemy_position(&mut app)), recorded);
+}